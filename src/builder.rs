@@ -0,0 +1,153 @@
+//! Setting the hook up in one go, see [`HookBuilder`].
+
+use anyhow::{Context as _, Result};
+use imgui::{FontGlyphRanges, Ui};
+use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+
+use crate::{
+    add_font_from_bytes, enable_debug_console, init_hook_with_targets, render_callback::RenderFn,
+    set_config, set_logging, set_passthrough, set_render_callback, set_toggle_key, update_config,
+    HookConfig, HookTargets, PresentHook,
+};
+
+struct Font {
+    data: Vec<u8>,
+    size_px: f32,
+    glyph_ranges: FontGlyphRanges,
+}
+
+/// Collects the hook's options and applies them, in the order they need, when it is installed.
+///
+/// ```ignore
+/// HookBuilder::new()
+///     .toggle_key(VK_F1)
+///     .render(|ui| ui.text("Hello"))
+///     .install()?;
+/// ```
+///
+/// Options left unset keep their current value, so the builder and the standalone setters can be
+/// mixed. Either way, nothing is read before the game presents its first frame after installing.
+#[derive(Default)]
+pub struct HookBuilder {
+    config: Option<HookConfig>,
+    present_hook: PresentHook,
+    targets: HookTargets,
+    swap_layer_buffers: Option<bool>,
+    toggle_key: Option<VIRTUAL_KEY>,
+    passthrough: Option<bool>,
+    fonts: Vec<Font>,
+    render: Option<RenderFn>,
+    debug_console: Option<bool>,
+    logging: Option<bool>,
+}
+
+impl HookBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the whole configuration, see [`set_config`].
+    pub fn config(mut self, config: HookConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Which present functions to detour, [`PresentHook::WglSwapBuffers`] by default.
+    pub fn present_hook(mut self, hooks: PresentHook) -> Self {
+        self.present_hook = hooks;
+        self
+    }
+
+    /// Module and function detoured instead of `opengl32.dll!wglSwapBuffers`, see
+    /// [`HookTargets::wgl_swap_buffers`].
+    pub fn target(mut self, module: &str, function: &str) -> Self {
+        self.targets.wgl_swap_buffers = (module.to_owned(), function.to_owned());
+        self
+    }
+
+    /// Replaces all present function targets, see [`HookTargets`].
+    pub fn targets(mut self, targets: HookTargets) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Also detour `wglSwapLayerBuffers`, see `HookConfig::hook_swap_layer_buffers`. Applies on
+    /// top of [`config`](Self::config).
+    pub fn swap_layer_buffers(mut self, enabled: bool) -> Self {
+        self.swap_layer_buffers = Some(enabled);
+        self
+    }
+
+    /// See [`set_toggle_key`].
+    pub fn toggle_key(mut self, vk: VIRTUAL_KEY) -> Self {
+        self.toggle_key = Some(vk);
+        self
+    }
+
+    /// See [`set_passthrough`].
+    pub fn passthrough(mut self, enabled: bool) -> Self {
+        self.passthrough = Some(enabled);
+        self
+    }
+
+    /// See [`add_font_from_bytes`]; fonts are added in the order given.
+    pub fn add_font(mut self, data: &[u8], size_px: f32, glyph_ranges: FontGlyphRanges) -> Self {
+        self.fonts.push(Font {
+            data: data.to_vec(),
+            size_px,
+            glyph_ranges,
+        });
+        self
+    }
+
+    /// See [`set_render_callback`].
+    pub fn render(mut self, f: impl FnMut(&Ui) + Send + 'static) -> Self {
+        self.render = Some(Box::new(f));
+        self
+    }
+
+    /// See [`enable_debug_console`]. Applied first, so everything after it is logged there.
+    pub fn debug_console(mut self, enabled: bool) -> Self {
+        self.debug_console = Some(enabled);
+        self
+    }
+
+    /// See [`set_logging`].
+    pub fn logging(mut self, enabled: bool) -> Self {
+        self.logging = Some(enabled);
+        self
+    }
+
+    /// Applies the options and hooks the present functions, see [`init_hook_with_targets`].
+    ///
+    /// Fails like the setters it calls, e.g. setting up the debug console; the options applied
+    /// until then stay.
+    pub fn install(self) -> Result<()> {
+        if let Some(enabled) = self.debug_console {
+            enable_debug_console(enabled).context("Failed setting up debug output")?;
+        }
+        if let Some(enabled) = self.logging {
+            set_logging(enabled);
+        }
+        if let Some(config) = self.config {
+            set_config(config);
+        }
+        if let Some(enabled) = self.swap_layer_buffers {
+            update_config(|config| config.hook_swap_layer_buffers = enabled);
+        }
+        if let Some(vk) = self.toggle_key {
+            set_toggle_key(vk);
+        }
+        if let Some(enabled) = self.passthrough {
+            set_passthrough(enabled);
+        }
+        for font in self.fonts {
+            add_font_from_bytes(&font.data, font.size_px, font.glyph_ranges);
+        }
+        if let Some(render) = self.render {
+            set_render_callback(render);
+        }
+
+        Ok(init_hook_with_targets(self.present_hook, &self.targets)?)
+    }
+}
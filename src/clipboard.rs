@@ -0,0 +1,90 @@
+//! Copy and paste for ImGui's text fields through the Windows clipboard.
+
+use imgui::ClipboardBackend;
+use std::{iter, ptr, slice};
+use windows::Win32::{
+    Foundation::HANDLE,
+    System::{
+        DataExchange::{
+            CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+        },
+        Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+    },
+    UI::WindowsAndMessaging::GetForegroundWindow,
+};
+
+// Standard clipboard format for nul-terminated UTF-16 text.
+const CF_UNICODETEXT: u32 = 13;
+
+pub(crate) struct WindowsClipboard;
+
+/// The clipboard, open for as long as this lives. It is shared by all processes, so every return
+/// path has to close it again.
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    /// Fails while another process has the clipboard open.
+    fn open() -> Option<Self> {
+        // The game window, as that is where CTRL+C was pressed. Without an owner `EmptyClipboard`
+        // leaves the clipboard to nobody and `SetClipboardData` fails.
+        let owner = unsafe { GetForegroundWindow() };
+        unsafe { OpenClipboard(owner) }
+            .as_bool()
+            .then_some(OpenedClipboard)
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        unsafe { CloseClipboard() };
+    }
+}
+
+impl ClipboardBackend for WindowsClipboard {
+    fn get(&mut self) -> Option<String> {
+        let _clipboard = OpenedClipboard::open()?;
+
+        // Fails when there is no text on the clipboard.
+        let data = unsafe { GetClipboardData(CF_UNICODETEXT) }.ok()?;
+        let text = unsafe { GlobalLock(data.0) } as *const u16;
+        if text.is_null() {
+            return None;
+        }
+
+        // Nul-terminated, but never read past the allocation in case it isn't.
+        let capacity = unsafe { GlobalSize(data.0) } / 2;
+        let units = unsafe { slice::from_raw_parts(text, capacity) };
+        let len = units.iter().position(|&unit| unit == 0).unwrap_or(capacity);
+        let value = String::from_utf16_lossy(&units[..len]);
+
+        unsafe { GlobalUnlock(data.0) };
+
+        Some(value)
+    }
+
+    fn set(&mut self, value: &str) {
+        let Some(_clipboard) = OpenedClipboard::open() else {
+            return;
+        };
+
+        let units: Vec<u16> = value.encode_utf16().chain(iter::once(0)).collect();
+        let memory = unsafe { GlobalAlloc(GMEM_MOVEABLE, units.len() * 2) };
+        if memory == 0 {
+            return;
+        }
+
+        let text = unsafe { GlobalLock(memory) } as *mut u16;
+        if text.is_null() {
+            unsafe { GlobalFree(memory) };
+            return;
+        }
+        unsafe { ptr::copy_nonoverlapping(units.as_ptr(), text, units.len()) };
+        unsafe { GlobalUnlock(memory) };
+
+        unsafe { EmptyClipboard() };
+        // The clipboard owns the memory once this succeeds.
+        if unsafe { SetClipboardData(CF_UNICODETEXT, HANDLE(memory)) }.is_err() {
+            unsafe { GlobalFree(memory) };
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use crate::{
+    input::{InputMode, MouseForwarding},
+    locate::FunctionLocation,
+    render_callback::DemoWindow,
+    renderer::RendererKind,
+};
+
+/// Options the hook reads on the render thread every frame.
+///
+/// Build one with `..Default::default()` and install it with [`set_config`], or tweak single
+/// fields through [`update_config`].
+#[derive(Clone, Debug)]
+pub struct HookConfig {
+    /// Backend used to draw the overlay. Read once, when the overlay initializes.
+    pub renderer: RendererKind,
+    /// How keyboard and mouse input reaches the overlay. Read once, when the overlay initializes.
+    pub input_mode: InputMode,
+    /// Also detour `opengl32.dll!wglSwapLayerBuffers`, which apps drawing into layer planes
+    /// present with, next to the functions picked by `PresentHook`. Off by default. Read when the
+    /// hooks are installed.
+    pub hook_swap_layer_buffers: bool,
+    /// Find `wglSwapBuffers` by its bytes or offset in its module instead of through
+    /// `GetProcAddress`, for packed or hooked `opengl32.dll`s whose export is missing or leads
+    /// somewhere else. `None` (the default) uses the export. Read when the hooks are installed.
+    pub wgl_swap_buffers_location: Option<FunctionLocation>,
+    /// Which mouse clicks the game still receives while the overlay is up. By default all but the
+    /// ones on the overlay.
+    pub mouse_forwarding: MouseForwarding,
+    /// Keep ALT+F4 from reaching the game. By default the chord is always forwarded, even while
+    /// ImGui has keyboard focus, so the game can still be closed.
+    pub swallow_close_chord: bool,
+    /// Turn `GL_DEPTH_TEST` off while the overlay draws (restored afterwards). On by default, a
+    /// depth test left enabled by the game is a common cause of an invisible overlay.
+    pub disable_depth_test: bool,
+    /// Clear the depth buffer where the overlay is drawn before drawing it.
+    pub clear_depth: bool,
+    /// Turn `GL_FRAMEBUFFER_SRGB` on while the overlay draws (restored afterwards). Off by
+    /// default, which keeps the game's setting with `RendererKind::OpenGl` and turns it off with
+    /// `RendererKind::Gl3`.
+    ///
+    /// For games rendering into an sRGB framebuffer. When on, the GL converts the overlay's
+    /// colors from linear to sRGB as they are written: ImGui's default colors, meant as sRGB,
+    /// come out lighter and washed out, a style with colors picked as linear values looks right.
+    pub srgb_framebuffer: bool,
+    /// File ImGui loads its layout from and saves it to. `None` (the default) keeps the layout
+    /// in memory only. Ignored when `set_ini_load`/`set_ini_save` callbacks are set. Read once,
+    /// when the overlay initializes.
+    pub ini_path: Option<PathBuf>,
+    /// The window shown while no render callback is set, with a checkbox for ImGui's demo window.
+    /// `None` shows nothing.
+    pub demo_window: Option<DemoWindow>,
+    /// Most characters buffered between two frames. When exceeded (a huge paste, a stuck key,
+    /// the overlay not rendering) the oldest ones are dropped and a warning is logged.
+    pub max_queued_chars: usize,
+    /// Rebuild the UI at most this often, drawing the previous frame's draw data again in
+    /// between. Bounds the overlay's CPU cost on hosts running at thousands of FPS. 0 (the
+    /// default) rebuilds every frame. In microseconds, so frame rate caps like 144 FPS come out
+    /// right. Needs GL 3.3, without it the UI is rebuilt every frame regardless (logged once).
+    pub min_render_interval_us: u32,
+    /// Wrap the overlay's GL calls in a `KHR_debug` debug group, so they show up as one labelled
+    /// block in RenderDoc/Nsight captures of the game. Does nothing without the extension.
+    pub debug_groups: bool,
+    /// Outline the clip rectangle of every ImGui draw command. Debugging aid, one frame behind.
+    #[cfg(feature = "debug-draw")]
+    pub debug_clip_rects: bool,
+    /// Also draw the overlay into a separate window created by the crate. Costly, see the
+    /// `experimental-mirror` feature in the README before turning it on.
+    #[cfg(feature = "experimental-mirror")]
+    pub mirror_window: bool,
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        HookConfig {
+            renderer: RendererKind::default(),
+            input_mode: InputMode::default(),
+            hook_swap_layer_buffers: false,
+            wgl_swap_buffers_location: None,
+            mouse_forwarding: MouseForwarding::default(),
+            swallow_close_chord: false,
+            disable_depth_test: true,
+            clear_depth: false,
+            srgb_framebuffer: false,
+            ini_path: None,
+            demo_window: Some(DemoWindow::default()),
+            max_queued_chars: 4096,
+            min_render_interval_us: 0,
+            debug_groups: false,
+            #[cfg(feature = "debug-draw")]
+            debug_clip_rects: false,
+            #[cfg(feature = "experimental-mirror")]
+            mirror_window: false,
+        }
+    }
+}
+
+static CONFIG: LazyLock<Mutex<HookConfig>> = LazyLock::new(Default::default);
+
+/// A copy of the current configuration.
+pub fn config() -> HookConfig {
+    CONFIG.lock().unwrap().clone()
+}
+
+pub fn set_config(config: HookConfig) {
+    *CONFIG.lock().unwrap() = config;
+}
+
+pub fn update_config(f: impl FnOnce(&mut HookConfig)) {
+    f(&mut CONFIG.lock().unwrap());
+}
+
+/// Rebuilds the overlay at most `fps` times per second, 0 (the default) for every frame the game
+/// presents. Shorthand for `HookConfig::min_render_interval_us`, see there; the game's own frame
+/// rate is untouched.
+pub fn set_overlay_max_fps(fps: u32) {
+    update_config(|config| {
+        config.min_render_interval_us = 1_000_000u32.checked_div(fps).unwrap_or(0)
+    });
+}
+
+/// Shorthand for setting `HookConfig::srgb_framebuffer`, see there.
+pub fn set_srgb_framebuffer(enabled: bool) {
+    update_config(|config| config.srgb_framebuffer = enabled);
+}
+
+/// Reads a value out of the configuration without cloning all of it.
+pub(crate) fn read<R>(f: impl FnOnce(&HookConfig) -> R) -> R {
+    f(&CONFIG.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn max_fps_keeps_sub_millisecond_precision() {
+        let _lock = test_util::lock();
+        let interval = || read(|config| config.min_render_interval_us);
+
+        set_overlay_max_fps(144);
+        assert_eq!(interval(), 6944);
+        set_overlay_max_fps(60);
+        assert_eq!(interval(), 16666);
+        set_overlay_max_fps(0);
+        assert_eq!(interval(), 0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Freeing the mouse cursor while the overlay wants the mouse, for games that hide it or lock it
+//! to the window (typical for first-person games), and handing it back afterwards. Also gives
+//! the cursor the shape ImGui asks for while it is over the overlay.
+
+use imgui::{ConfigFlags, Io, MouseCursor, Ui};
+use std::{mem, ptr, sync::Mutex};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{HINSTANCE, RECT},
+        UI::WindowsAndMessaging::{
+            ClipCursor, GetClipCursor, GetCursorInfo, LoadCursorW, SetCursor, ShowCursor,
+            CURSORINFO, CURSOR_SHOWING, HCURSOR, IDC_ARROW, IDC_HAND, IDC_IBEAM, IDC_NO,
+            IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE,
+        },
+    },
+};
+
+// Upper bound on `ShowCursor` calls, games can push the display count arbitrarily low.
+const MAX_SHOW_CALLS: u32 = 64;
+
+/// What was changed to free the cursor, to undo exactly that.
+struct Release {
+    /// The game's clip rectangle, the whole screen if it had none.
+    clip: Option<RECT>,
+    /// How often `ShowCursor(TRUE)` was called.
+    shown: u32,
+}
+
+static RELEASE: Mutex<Option<Release>> = Mutex::new(None);
+
+/// Shape the cursor gets over the game window, `None` to leave it to the game. `Some(None)`
+/// hides it, for when ImGui draws the cursor itself.
+static SHAPE: Mutex<Option<Option<MouseCursor>>> = Mutex::new(None);
+
+/// Whether the OS draws a cursor: shown, and not set to none (`SetCursor(NULL)`).
+fn os_cursor_visible() -> bool {
+    let mut info = CURSORINFO {
+        cbSize: mem::size_of::<CURSORINFO>() as u32,
+        ..Default::default()
+    };
+
+    unsafe { GetCursorInfo(&mut info) }.as_bool()
+        && info.flags.0 & CURSOR_SHOWING.0 != 0
+        && info.hCursor.0 != 0
+}
+
+fn restore(release: &mut Option<Release>) {
+    let Some(release) = release.take() else {
+        return;
+    };
+
+    for _ in 0..release.shown {
+        unsafe { ShowCursor(false) };
+    }
+    if let Some(clip) = release.clip {
+        unsafe { ClipCursor(&clip) };
+    }
+}
+
+/// Frees the cursor while `wanted` (the overlay is shown and wants the mouse) and gives it back
+/// to the game once it isn't.
+///
+/// Call before building a frame, on the game window's thread: the display count `ShowCursor`
+/// changes belongs to the calling thread.
+pub(crate) fn update(io: &mut Io, wanted: bool) {
+    let mut release = RELEASE.lock().unwrap();
+
+    if !wanted {
+        restore(&mut release);
+        io.mouse_draw_cursor = false;
+        return;
+    }
+
+    if release.is_none() {
+        let mut clip = RECT::default();
+        let clip = unsafe { GetClipCursor(&mut clip) }
+            .as_bool()
+            .then_some(clip);
+
+        let mut shown = 0;
+        while shown < MAX_SHOW_CALLS {
+            shown += 1;
+            if unsafe { ShowCursor(true) } >= 0 {
+                break;
+            }
+        }
+
+        *release = Some(Release { clip, shown });
+    }
+
+    // Games tend to clip the cursor again every frame.
+    unsafe { ClipCursor(ptr::null()) };
+
+    // Games hiding the cursor with `SetCursor(NULL)` instead keep it hidden, ImGui draws one then.
+    io.mouse_draw_cursor = !os_cursor_visible();
+}
+
+/// Gives the cursor back to the game, for when the overlay goes away.
+pub(crate) fn reset() {
+    restore(&mut RELEASE.lock().unwrap());
+    *SHAPE.lock().unwrap() = None;
+}
+
+fn system_cursor(cursor: MouseCursor) -> PCWSTR {
+    match cursor {
+        MouseCursor::Arrow => IDC_ARROW,
+        MouseCursor::TextInput => IDC_IBEAM,
+        MouseCursor::ResizeAll => IDC_SIZEALL,
+        MouseCursor::ResizeNS => IDC_SIZENS,
+        MouseCursor::ResizeEW => IDC_SIZEWE,
+        MouseCursor::ResizeNESW => IDC_SIZENESW,
+        MouseCursor::ResizeNWSE => IDC_SIZENWSE,
+        MouseCursor::Hand => IDC_HAND,
+        MouseCursor::NotAllowed => IDC_NO,
+    }
+}
+
+/// Remembers the cursor shape the frame being built asks for, for [`apply_shape`]. `wanted` as
+/// for [`update`].
+pub(crate) fn update_shape(ui: &Ui, wanted: bool) {
+    let io = ui.io();
+    let shape = (wanted
+        && !io
+            .config_flags
+            .contains(ConfigFlags::NO_MOUSE_CURSOR_CHANGE))
+    .then(|| {
+        if io.mouse_draw_cursor {
+            None
+        } else {
+            ui.mouse_cursor()
+        }
+    });
+
+    *SHAPE.lock().unwrap() = shape;
+}
+
+/// Sets the cursor to the shape ImGui asked for last frame, returns false (and does nothing) when
+/// the overlay doesn't want the mouse.
+///
+/// For `WM_SETCURSOR`, which games answer by setting their own cursor.
+pub(crate) fn apply_shape() -> bool {
+    let Some(shape) = *SHAPE.lock().unwrap() else {
+        return false;
+    };
+
+    let cursor = match shape {
+        Some(cursor) => {
+            unsafe { LoadCursorW(HINSTANCE(0), system_cursor(cursor)) }.unwrap_or(HCURSOR(0))
+        }
+        None => HCURSOR(0),
+    };
+    unsafe { SetCursor(cursor) };
+
+    true
+}
@@ -0,0 +1,40 @@
+//! Outlines of the clip rectangles ImGui submitted in the previous frame.
+
+use imgui::{DrawCmd, DrawData, Ui};
+use std::sync::Mutex;
+
+const CLIP_RECT_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+static CLIP_RECTS: Mutex<Vec<[f32; 4]>> = Mutex::new(Vec::new());
+
+/// Records the clip rects of the frame that was just rendered.
+pub(crate) fn capture_last_frame() {
+    // The renderer consumes the `Ui`, but the draw data stays valid until the next frame starts.
+    let draw_data = unsafe { (imgui::sys::igGetDrawData() as *const DrawData).as_ref() };
+
+    let mut rects = CLIP_RECTS.lock().unwrap();
+    rects.clear();
+
+    if let Some(draw_data) = draw_data {
+        for draw_list in draw_data.draw_lists() {
+            for cmd in draw_list.commands() {
+                if let DrawCmd::Elements { cmd_params, .. } = cmd {
+                    let [x1, y1, x2, y2] = cmd_params.clip_rect;
+                    let [ox, oy] = draw_data.display_pos;
+                    rects.push([x1 - ox, y1 - oy, x2 - ox, y2 - oy]);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the recorded rects on top of everything else in the current frame.
+pub(crate) fn draw(ui: &Ui) {
+    let draw_list = ui.get_foreground_draw_list();
+
+    for [x1, y1, x2, y2] in CLIP_RECTS.lock().unwrap().iter().copied() {
+        draw_list
+            .add_rect([x1, y1], [x2, y2], CLIP_RECT_COLOR)
+            .build();
+    }
+}
@@ -0,0 +1,108 @@
+//! Unloading the hook DLL from the running game with a key press, see
+//! [`set_eject_key`](crate::set_eject_key). Meant for development, to load a rebuilt overlay
+//! without restarting the game.
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+use windows::Win32::{Foundation::HINSTANCE, System::LibraryLoader::FreeLibraryAndExitThread};
+
+// Our own module as `DllMain` got it, 0 when the crate isn't loaded as the hook DLL.
+static MODULE: AtomicIsize = AtomicIsize::new(0);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+// Threads currently inside a present detour, the window procedure or the message hook, the DLL
+// can't go while any is. Those are all the ways the game calls into us.
+static IN_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn set_module(module: HINSTANCE) {
+    MODULE.store(module.0, Ordering::Relaxed);
+}
+
+/// Asks for the hook to be ejected once the current frame has been presented.
+pub(crate) fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Counts the thread as inside the hook for as long as it lives, so the DLL isn't freed under it.
+/// Taken first thing by every function the game calls into.
+pub(crate) struct HookGuard;
+
+impl HookGuard {
+    pub(crate) fn enter() -> Self {
+        IN_HOOK.fetch_add(1, Ordering::AcqRel);
+        HookGuard
+    }
+}
+
+impl Drop for HookGuard {
+    fn drop(&mut self) {
+        IN_HOOK.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Blocks until no thread is inside the hook. A thread in the window procedure may stay for long,
+/// e.g. in the modal loop of a window being moved.
+fn wait_until_left() {
+    while IN_HOOK.load(Ordering::Acquire) != 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Shuts the hook down and unloads the DLL if an eject was requested. Call at the very end of a
+/// present detour, with nothing locked.
+pub(crate) fn run_if_requested() {
+    if !REQUESTED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    log_info!("Ejecting");
+    // Also restores the window procedure and removes the message hook, so no new calls come in.
+    if let Err(e) = crate::shutdown_hook() {
+        log_error!("Failed shutting down hook: {}", e);
+        return;
+    }
+
+    // Embedded in someone else's module there is nothing to unload.
+    let module = MODULE.load(Ordering::Relaxed);
+    if module == 0 {
+        return;
+    }
+
+    // The calling thread is still in our code, the DLL is freed from a thread of its own once
+    // everyone has left.
+    let spawned = thread::Builder::new()
+        .name("opengl-imgui-hook eject".to_owned())
+        .spawn(move || {
+            wait_until_left();
+            unsafe { FreeLibraryAndExitThread(HINSTANCE(module), 0) }
+        });
+
+    if let Err(e) = spawned {
+        log_error!("Failed spawning eject thread: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time::Instant};
+
+    #[test]
+    fn waits_for_threads_in_the_hook() {
+        let (entered, wait_entered) = mpsc::channel();
+        let in_window_procedure = thread::spawn(move || {
+            let _guard = HookGuard::enter();
+            entered.send(()).unwrap();
+            // A modal loop inside the game's window procedure.
+            thread::sleep(Duration::from_millis(50));
+        });
+        wait_entered.recv().unwrap();
+
+        let start = Instant::now();
+        wait_until_left();
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        in_window_procedure.join().unwrap();
+    }
+}
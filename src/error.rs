@@ -0,0 +1,107 @@
+use std::{error::Error, fmt};
+
+/// Errors the hook reports for bad input, and for Win32/detour failures callers may want to tell
+/// apart. [`init_hook`](crate::init_hook) returns them as they are; functions returning `anyhow`
+/// errors still return these, match on them with `error.downcast_ref::<HookError>()`. Other
+/// failures of the Win32/GL calls come back as plain `anyhow` errors with the `GetLastError` code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookError {
+    /// A module or function name contained a nul byte and can't be passed to Win32.
+    InvalidName(String),
+    /// A window handle that doesn't name a window (anymore).
+    InvalidWindow(isize),
+    /// A byte pattern that isn't hex bytes and wildcards, or only wildcards.
+    InvalidPattern(String),
+    /// The module with the present function is neither loaded nor loadable.
+    ModuleNotFound { module: String, last_error: u32 },
+    /// The module doesn't export the present function.
+    ProcAddressFailed { function: String, last_error: u32 },
+    /// No console could be allocated for the debug output.
+    ConsoleAllocFailed { last_error: u32 },
+    /// The console couldn't be freed, or the output couldn't be redirected to or from it.
+    ConsoleFailed { function: String, last_error: u32 },
+    /// `HookConfig::wgl_swap_buffers_location` doesn't lead to the present function.
+    LocateFailed { function: String, reason: String },
+    /// The present function couldn't be detoured, e.g. its first instructions can't be relocated.
+    DetourInit { function: String, reason: String },
+    /// The detour of the present function couldn't be turned on, e.g. its code can't be written.
+    DetourEnable { function: String, reason: String },
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::InvalidName(name) => write!(f, "Invalid name (contains nul): {:?}", name),
+            HookError::InvalidWindow(hwnd) => write!(f, "Not a window: {:#x}", hwnd),
+            HookError::InvalidPattern(pattern) => write!(f, "Invalid byte pattern: {:?}", pattern),
+            HookError::ModuleNotFound { module, last_error } => write!(
+                f,
+                "Failed loading module {}, GetLastError: {}",
+                module, last_error
+            ),
+            HookError::ProcAddressFailed {
+                function,
+                last_error,
+            } => write!(
+                f,
+                "Failed GetProcAddress for {}, GetLastError: {}",
+                function, last_error
+            ),
+            HookError::ConsoleAllocFailed { last_error } => {
+                write!(f, "Failed allocating console, GetLastError: {}", last_error)
+            }
+            HookError::ConsoleFailed {
+                function,
+                last_error,
+            } => write!(
+                f,
+                "Failed {} for the debug output, GetLastError: {}",
+                function, last_error
+            ),
+            HookError::LocateFailed { function, reason } => {
+                write!(f, "Failed locating {}: {}", function, reason)
+            }
+            HookError::DetourInit { function, reason } => {
+                write!(f, "Failed initializing {} detour: {}", function, reason)
+            }
+            HookError::DetourEnable { function, reason } => {
+                write!(f, "Failed enabling {} detour: {}", function, reason)
+            }
+        }
+    }
+}
+
+impl Error for HookError {}
+
+/// The `GetLastError` code a failed `windows` call captured in `error`, for the variants above.
+/// Codes that don't come from Win32 are passed on as the whole `HRESULT`.
+pub(crate) fn win32_code(error: &windows::core::Error) -> u32 {
+    let code = error.code().0 as u32;
+    // HRESULT_FROM_WIN32: severity error, FACILITY_WIN32, the code in the low word.
+    if code & 0xFFFF_0000 == 0x8007_0000 {
+        code & 0xFFFF
+    } else {
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::core::{Error, HRESULT};
+
+    #[test]
+    fn win32_code_unwraps_win32_errors() {
+        // ERROR_MOD_NOT_FOUND, as LoadLibraryA reports it.
+        let error = Error::from(HRESULT(0x8007_007E_u32 as i32));
+
+        assert_eq!(win32_code(&error), 126);
+    }
+
+    #[test]
+    fn win32_code_keeps_other_hresults() {
+        let error = Error::from(HRESULT(0x8000_4005_u32 as i32));
+
+        assert_eq!(win32_code(&error), 0x8000_4005);
+    }
+}
@@ -0,0 +1,82 @@
+//! Fonts to use instead of ImGui's built-in one, see [`add_font_from_bytes`] and
+//! [`reload_fonts`].
+
+use imgui::{FontAtlas, FontConfig, FontGlyphRanges, FontSource};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// Size of ImGui's built-in font, in pixels.
+const DEFAULT_FONT_SIZE: f32 = 13.0;
+
+struct FontRequest {
+    data: Vec<u8>,
+    size_px: f32,
+    glyph_ranges: FontGlyphRanges,
+}
+
+// SAFETY: `FontGlyphRanges` only points at ImGui's built-in tables or a `&'static [u32]`, both
+// immutable for the life of the process.
+unsafe impl Send for FontRequest {}
+
+static FONTS: Mutex<Vec<FontRequest>> = Mutex::new(Vec::new());
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Adds a TTF/OTF font to the overlay, e.g. for CJK text (`FontGlyphRanges::japanese()` and
+/// friends) or branding. The first font added becomes the UI's default, `ui.push_font` picks the
+/// others in the order they were added.
+///
+/// The renderer bakes the font atlas when it is created: fonts added once the overlay is up only
+/// appear after [`reload_fonts`].
+pub fn add_font_from_bytes(data: &[u8], size_px: f32, glyph_ranges: FontGlyphRanges) {
+    FONTS.lock().unwrap().push(FontRequest {
+        data: data.to_vec(),
+        size_px,
+        glyph_ranges,
+    });
+}
+
+/// Bakes the font atlas again with the fonts added so far and recreates the renderer's font
+/// texture, for fonts loaded on demand once the overlay is up. Only queues the rebuild, it happens
+/// on the render thread before the next frame; failures there are logged. Before the overlay comes
+/// up there is nothing to rebuild, the fonts are baked when it does.
+pub fn reload_fonts() {
+    RELOAD_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether [`reload_fonts`] was called since the last time this was asked.
+pub(crate) fn take_reload_request() -> bool {
+    RELOAD_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Replaces the fonts in `atlas` with the requested ones (or the built-in one), rasterized
+/// `scale` times larger. The caller builds the atlas.
+pub(crate) fn add_fonts(atlas: &mut FontAtlas, scale: f32) {
+    let fonts = FONTS.lock().unwrap();
+    // Whatever was asked for is in this atlas.
+    RELOAD_REQUESTED.store(false, Ordering::Relaxed);
+
+    atlas.clear();
+
+    if fonts.is_empty() {
+        atlas.add_font(&[FontSource::DefaultFontData {
+            config: Some(FontConfig {
+                size_pixels: DEFAULT_FONT_SIZE * scale,
+                ..FontConfig::default()
+            }),
+        }]);
+        return;
+    }
+
+    for font in fonts.iter() {
+        atlas.add_font(&[FontSource::TtfData {
+            data: &font.data,
+            size_pixels: font.size_px * scale,
+            config: Some(FontConfig {
+                glyph_ranges: font.glyph_ranges.clone(),
+                ..FontConfig::default()
+            }),
+        }]);
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    ffi::{c_char, c_void, CStr},
+    mem,
+    sync::{
+        atomic::{AtomicI32, AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use crate::gl_renderer;
+
+pub type GLenum = u32;
+pub type GLbitfield = u32;
+pub type GLboolean = u8;
+pub type GLint = i32;
+pub type GLuint = u32;
+pub type GLsizei = i32;
+pub type GLubyte = u8;
+
+pub const GL_VERSION: GLenum = 0x1F02;
+pub const GL_EXTENSIONS: GLenum = 0x1F03;
+pub const GL_NUM_EXTENSIONS: GLenum = 0x821D;
+pub const GL_MAJOR_VERSION: GLenum = 0x821B;
+pub const GL_MINOR_VERSION: GLenum = 0x821C;
+pub const GL_DEPTH_TEST: GLenum = 0x0B71;
+pub const GL_DEPTH_WRITEMASK: GLenum = 0x0B72;
+pub const GL_VIEWPORT: GLenum = 0x0BA2;
+pub const GL_SCISSOR_BOX: GLenum = 0x0C10;
+pub const GL_SCISSOR_TEST: GLenum = 0x0C11;
+pub const GL_FRAMEBUFFER_SRGB: GLenum = 0x8DB9;
+pub const GL_DRAW_FRAMEBUFFER_BINDING: GLenum = 0x8CA6;
+pub const GL_READ_FRAMEBUFFER: GLenum = 0x8CA8;
+pub const GL_DRAW_FRAMEBUFFER: GLenum = 0x8CA9;
+pub const GL_READ_FRAMEBUFFER_BINDING: GLenum = 0x8CAA;
+pub const GL_DEPTH_BUFFER_BIT: GLbitfield = 0x0000_0100;
+pub const GL_TRUE: GLboolean = 1;
+pub const GL_DEBUG_SOURCE_APPLICATION: GLenum = 0x824A;
+
+type FnViewport = unsafe extern "system" fn(GLint, GLint, GLsizei, GLsizei);
+type FnScissor = unsafe extern "system" fn(GLint, GLint, GLsizei, GLsizei);
+type FnGetString = unsafe extern "system" fn(GLenum) -> *const GLubyte;
+type FnGetStringi = unsafe extern "system" fn(GLenum, GLuint) -> *const GLubyte;
+type FnGetIntegerv = unsafe extern "system" fn(GLenum, *mut GLint);
+type FnGetBooleanv = unsafe extern "system" fn(GLenum, *mut GLboolean);
+type FnCap = unsafe extern "system" fn(GLenum);
+type FnIsEnabled = unsafe extern "system" fn(GLenum) -> GLboolean;
+type FnClear = unsafe extern "system" fn(GLbitfield);
+type FnDepthMask = unsafe extern "system" fn(GLboolean);
+type FnPushDebugGroup = unsafe extern "system" fn(GLenum, GLuint, GLsizei, *const c_char);
+type FnPopDebugGroup = unsafe extern "system" fn();
+type FnReadPixels =
+    unsafe extern "system" fn(GLint, GLint, GLsizei, GLsizei, GLenum, GLenum, *mut c_void);
+type FnPixelStorei = unsafe extern "system" fn(GLenum, GLint);
+type FnBindBuffer = unsafe extern "system" fn(GLenum, GLuint);
+type FnBindFramebuffer = unsafe extern "system" fn(GLenum, GLuint);
+
+/// The handful of GL entry points the hook calls itself, resolved from the game's context.
+#[derive(Clone, Copy)]
+pub struct Gl {
+    pub viewport: FnViewport,
+    pub scissor: FnScissor,
+    pub get_string: FnGetString,
+    pub get_integerv: FnGetIntegerv,
+    pub get_booleanv: FnGetBooleanv,
+    pub enable: FnCap,
+    pub disable: FnCap,
+    pub is_enabled: FnIsEnabled,
+    pub clear: FnClear,
+    pub depth_mask: FnDepthMask,
+    pub read_pixels: FnReadPixels,
+    pub pixel_storei: FnPixelStorei,
+    /// GL 1.5+ only.
+    pub bind_buffer: Option<FnBindBuffer>,
+    /// GL 3.0+ or `ARB_framebuffer_object` only.
+    pub bind_framebuffer: Option<FnBindFramebuffer>,
+    /// GL 3.0+ only.
+    pub get_stringi: Option<FnGetStringi>,
+    /// `KHR_debug`. Drivers may hand these out without supporting the extension, see
+    /// `debug_group`.
+    pub push_debug_group: Option<FnPushDebugGroup>,
+    pub pop_debug_group: Option<FnPopDebugGroup>,
+}
+
+struct GlInfo {
+    version: Option<String>,
+    version_number: Option<(u32, u32)>,
+    extensions: Vec<String>,
+}
+
+// Both belong to the context the overlay was set up on, see `load` and `unload`.
+static GL: Mutex<Option<Gl>> = Mutex::new(None);
+static INFO: Mutex<Option<GlInfo>> = Mutex::new(None);
+
+// Where the renderer's (0, 0) ends up in window coordinates, see `set_draw_origin`.
+static DRAW_ORIGIN: [AtomicI32; 2] = [AtomicI32::new(0), AtomicI32::new(0)];
+// The framebuffer the overlay is drawn into, see `set_target_framebuffer`.
+static TARGET_FRAMEBUFFER: AtomicU32 = AtomicU32::new(0);
+
+/// Where every GL function the crate calls comes from: the driver, or the mock with `mock-gl`.
+fn proc_address(name: &str) -> *const c_void {
+    #[cfg(feature = "mock-gl")]
+    return crate::mock_gl::proc_address(name);
+    #[cfg(not(feature = "mock-gl"))]
+    gl_loader::get_proc_address(name)
+}
+
+pub(crate) fn load_fn<T: Copy>(name: &str) -> Option<T> {
+    let ptr = proc_address(name);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { mem::transmute_copy(&ptr) })
+    }
+}
+
+/// Resolves the function tables from the current context, replacing those of the one the overlay
+/// was set up on before. `gl_loader::init_gl` must have been called beforehand.
+pub fn load() -> bool {
+    let gl = (|| {
+        Some(Gl {
+            viewport: load_fn("glViewport")?,
+            scissor: load_fn("glScissor")?,
+            get_string: load_fn("glGetString")?,
+            get_integerv: load_fn("glGetIntegerv")?,
+            get_booleanv: load_fn("glGetBooleanv")?,
+            enable: load_fn("glEnable")?,
+            disable: load_fn("glDisable")?,
+            is_enabled: load_fn("glIsEnabled")?,
+            clear: load_fn("glClear")?,
+            depth_mask: load_fn("glDepthMask")?,
+            read_pixels: load_fn("glReadPixels")?,
+            pixel_storei: load_fn("glPixelStorei")?,
+            bind_buffer: load_fn("glBindBuffer"),
+            bind_framebuffer: load_fn("glBindFramebuffer"),
+            get_stringi: load_fn("glGetStringi"),
+            push_debug_group: load_fn("glPushDebugGroup"),
+            pop_debug_group: load_fn("glPopDebugGroup"),
+        })
+    })();
+
+    // The renderer's GL 3.3 functions come from the same context.
+    gl_renderer::load_functions();
+
+    let loaded = gl.is_some();
+    *GL.lock().unwrap() = gl;
+    loaded
+}
+
+/// Forgets the function tables and the context info, for when the overlay is dropped. Whatever
+/// context it comes up on next is loaded afresh.
+pub fn unload() {
+    *GL.lock().unwrap() = None;
+    *INFO.lock().unwrap() = None;
+    gl_renderer::unload_functions();
+}
+
+pub fn get() -> Option<Gl> {
+    *GL.lock().unwrap()
+}
+
+unsafe fn gl_string(ptr: *const GLubyte) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(
+            CStr::from_ptr(ptr as *const c_char)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+fn query_extensions(gl: &Gl) -> Vec<String> {
+    // Core profiles only answer the indexed query, `glGetString(GL_EXTENSIONS)` is an error there.
+    if let Some(get_stringi) = gl.get_stringi {
+        let mut count = 0;
+        unsafe { (gl.get_integerv)(GL_NUM_EXTENSIONS, &mut count) };
+
+        if count > 0 {
+            return (0..count as GLuint)
+                .filter_map(|i| unsafe { gl_string(get_stringi(GL_EXTENSIONS, i)) })
+                .collect();
+        }
+    }
+
+    unsafe { gl_string((gl.get_string)(GL_EXTENSIONS)) }
+        .map(|all| all.split_whitespace().map(str::to_owned).collect())
+        .unwrap_or_default()
+}
+
+/// The "major.minor" at the start of a `GL_VERSION` string, skipping an "OpenGL ES " prefix.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let version = version.trim_start_matches("OpenGL ES ");
+    let mut numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(str::parse::<u32>);
+    match (numbers.next()?, numbers.next()?) {
+        (Ok(major), Ok(minor)) => Some((major, minor)),
+        _ => None,
+    }
+}
+
+fn query_version_number(gl: &Gl, version: Option<&str>) -> Option<(u32, u32)> {
+    // Only GL 3.0+ knows the integer queries, older contexts leave the values alone.
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        (gl.get_integerv)(GL_MAJOR_VERSION, &mut major);
+        (gl.get_integerv)(GL_MINOR_VERSION, &mut minor);
+    }
+    if major >= 3 {
+        return Some((major as u32, minor as u32));
+    }
+
+    version.and_then(parse_version)
+}
+
+/// Caches version and extension strings of the current context. Called on the render thread after
+/// `load`, whenever the overlay is set up.
+pub fn query_info() {
+    let info = get().map(|gl| {
+        let version = unsafe { gl_string((gl.get_string)(GL_VERSION)) };
+        GlInfo {
+            version_number: query_version_number(&gl, version.as_deref()),
+            version,
+            extensions: query_extensions(&gl),
+        }
+    });
+
+    *INFO.lock().unwrap() = info;
+}
+
+/// The `GL_VERSION` string of the game's context, `None` while the overlay isn't up.
+pub fn gl_version() -> Option<String> {
+    INFO.lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|info| info.version.clone())
+}
+
+/// Major and minor version of the game's context, `None` while the overlay isn't up or if the
+/// driver reports garbage.
+pub fn version_number() -> Option<(u32, u32)> {
+    INFO.lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|info| info.version_number)
+}
+
+/// Extensions supported by the game's context, empty while the overlay isn't up.
+pub fn gl_extensions() -> Vec<String> {
+    INFO.lock()
+        .unwrap()
+        .as_ref()
+        .map(|info| info.extensions.clone())
+        .unwrap_or_default()
+}
+
+pub fn has_extension(name: &str) -> bool {
+    INFO.lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|info| info.extensions.iter().any(|ext| ext == name))
+}
+
+/// Pops the debug group pushed by `debug_group` when dropped.
+pub struct DebugGroup(FnPopDebugGroup);
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        unsafe { (self.0)() };
+    }
+}
+
+/// Opens a debug group named `label`, so graphics debuggers (RenderDoc, Nsight) show the calls
+/// up to the guard's drop as one labelled block. `None` without `KHR_debug`.
+pub fn debug_group(label: &CStr) -> Option<DebugGroup> {
+    let gl = get()?;
+    if !has_extension("GL_KHR_debug") {
+        return None;
+    }
+    let (push, pop) = (gl.push_debug_group?, gl.pop_debug_group?);
+
+    // A length of -1 means nul-terminated.
+    unsafe { push(GL_DEBUG_SOURCE_APPLICATION, 0, -1, label.as_ptr()) };
+
+    Some(DebugGroup(pop))
+}
+
+/// Shifts everything the renderer draws by `(x, y)` in GL window coordinates (origin bottom-left).
+pub fn set_draw_origin(x: GLint, y: GLint) {
+    DRAW_ORIGIN[0].store(x, Ordering::Relaxed);
+    DRAW_ORIGIN[1].store(y, Ordering::Relaxed);
+}
+
+pub fn draw_origin() -> (GLint, GLint) {
+    (
+        DRAW_ORIGIN[0].load(Ordering::Relaxed),
+        DRAW_ORIGIN[1].load(Ordering::Relaxed),
+    )
+}
+
+/// Draws the overlay into the framebuffer object `fbo` instead of the window's, for games that
+/// present a framebuffer object of their own (e.g. through an interop layer). 0, the default, is
+/// the window. Whatever the game has bound at the swap is bound again after the overlay.
+/// Screenshots are read from it as well.
+pub fn set_target_framebuffer(fbo: GLuint) {
+    TARGET_FRAMEBUFFER.store(fbo, Ordering::Relaxed);
+}
+
+pub fn target_framebuffer() -> GLuint {
+    TARGET_FRAMEBUFFER.load(Ordering::Relaxed)
+}
+
+unsafe extern "system" fn viewport_shim(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    if let Some(gl) = get() {
+        let (ox, oy) = draw_origin();
+        (gl.viewport)(x + ox, y + oy, width, height);
+    }
+}
+
+unsafe extern "system" fn scissor_shim(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    if let Some(gl) = get() {
+        let (ox, oy) = draw_origin();
+        (gl.scissor)(x + ox, y + oy, width, height);
+    }
+}
+
+/// Proc loader handed to `imgui_opengl_renderer`.
+///
+/// The renderer always draws to `(0, 0, display_size)`, so its viewport and scissor calls are
+/// routed through shims that apply the draw origin. Everything else goes straight to the driver.
+pub fn renderer_proc_address(name: &str) -> *const c_void {
+    match name {
+        "glViewport" => viewport_shim as *const c_void,
+        "glScissor" => scissor_shim as *const c_void,
+        _ => proc_address(name),
+    }
+}
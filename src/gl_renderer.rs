@@ -0,0 +1,752 @@
+//! A small ImGui renderer drawing `DrawData` with GL 3.3 core functionality.
+//!
+//! Unlike `imgui_opengl_renderer` it renders draw data that has already been produced, so the same
+//! frame can be drawn more than once (e.g. into the mirror window) and with a projection of the
+//! caller's choosing. The GL state it changes is restored afterwards. It is also the overlay's
+//! renderer with `RendererKind::Gl3`, together with a [`FontTexture`].
+//!
+//! Requires a GL 3.3 context, core or compatibility profile. ImGui's colors are sRGB already, so
+//! `GL_FRAMEBUFFER_SRGB` is turned off while drawing to keep them from being converted twice,
+//! unless `HookConfig::srgb_framebuffer` asks for the conversion.
+
+use anyhow::{anyhow, Result};
+use imgui::{Context, DrawCmd, DrawData, DrawIdx, DrawVert, TextureId};
+use std::{
+    ffi::{c_char, c_void, CString},
+    mem, ptr,
+    sync::Mutex,
+};
+
+use crate::{
+    config,
+    gl::{
+        self, load_fn, GLboolean, GLenum, GLint, GLsizei, GLuint, Gl, GL_DEPTH_TEST,
+        GL_DEPTH_WRITEMASK, GL_FRAMEBUFFER_SRGB, GL_SCISSOR_BOX, GL_SCISSOR_TEST, GL_TRUE,
+    },
+};
+
+type GLfloat = f32;
+type GLsizeiptr = isize;
+
+const GL_FALSE: GLboolean = 0;
+const GL_ONE: GLenum = 1;
+const GL_TRIANGLES: GLenum = 0x0004;
+const GL_SRC_ALPHA: GLenum = 0x0302;
+const GL_ONE_MINUS_SRC_ALPHA: GLenum = 0x0303;
+const GL_CULL_FACE: GLenum = 0x0B44;
+const GL_STENCIL_TEST: GLenum = 0x0B90;
+const GL_BLEND: GLenum = 0x0BE2;
+const GL_TEXTURE_2D: GLenum = 0x0DE1;
+const GL_UNSIGNED_BYTE: GLenum = 0x1401;
+const GL_UNSIGNED_SHORT: GLenum = 0x1403;
+const GL_FLOAT: GLenum = 0x1406;
+const GL_RGBA: GLenum = 0x1908;
+const GL_LINEAR: GLint = 0x2601;
+const GL_TEXTURE_MAG_FILTER: GLenum = 0x2800;
+const GL_TEXTURE_MIN_FILTER: GLenum = 0x2801;
+const GL_UNPACK_ROW_LENGTH: GLenum = 0x0CF2;
+const GL_FUNC_ADD: GLenum = 0x8006;
+const GL_TEXTURE0: GLenum = 0x84C0;
+const GL_ARRAY_BUFFER: GLenum = 0x8892;
+const GL_ELEMENT_ARRAY_BUFFER: GLenum = 0x8893;
+const GL_STREAM_DRAW: GLenum = 0x88E0;
+const GL_FRAGMENT_SHADER: GLenum = 0x8B30;
+const GL_VERTEX_SHADER: GLenum = 0x8B31;
+const GL_COMPILE_STATUS: GLenum = 0x8B81;
+const GL_LINK_STATUS: GLenum = 0x8B82;
+const GL_INFO_LOG_LENGTH: GLenum = 0x8B84;
+const GL_VIEWPORT: GLenum = 0x0BA2;
+const GL_BLEND_EQUATION_RGB: GLenum = 0x8009;
+const GL_TEXTURE_BINDING_2D: GLenum = 0x8069;
+const GL_BLEND_DST_RGB: GLenum = 0x80C8;
+const GL_BLEND_SRC_RGB: GLenum = 0x80C9;
+const GL_BLEND_DST_ALPHA: GLenum = 0x80CA;
+const GL_BLEND_SRC_ALPHA: GLenum = 0x80CB;
+const GL_ACTIVE_TEXTURE: GLenum = 0x84E0;
+const GL_VERTEX_ARRAY_BINDING: GLenum = 0x85B5;
+const GL_BLEND_EQUATION_ALPHA: GLenum = 0x883D;
+const GL_ARRAY_BUFFER_BINDING: GLenum = 0x8894;
+const GL_ELEMENT_ARRAY_BUFFER_BINDING: GLenum = 0x8895;
+const GL_CURRENT_PROGRAM: GLenum = 0x8B8D;
+
+/// GL 3.3 entry points the renderer needs on top of `gl::Gl`.
+#[derive(Clone, Copy)]
+struct Functions {
+    create_shader: unsafe extern "system" fn(GLenum) -> GLuint,
+    shader_source: unsafe extern "system" fn(GLuint, GLsizei, *const *const c_char, *const GLint),
+    compile_shader: unsafe extern "system" fn(GLuint),
+    get_shaderiv: unsafe extern "system" fn(GLuint, GLenum, *mut GLint),
+    get_shader_info_log: unsafe extern "system" fn(GLuint, GLsizei, *mut GLsizei, *mut c_char),
+    delete_shader: unsafe extern "system" fn(GLuint),
+    create_program: unsafe extern "system" fn() -> GLuint,
+    attach_shader: unsafe extern "system" fn(GLuint, GLuint),
+    link_program: unsafe extern "system" fn(GLuint),
+    get_programiv: unsafe extern "system" fn(GLuint, GLenum, *mut GLint),
+    get_program_info_log: unsafe extern "system" fn(GLuint, GLsizei, *mut GLsizei, *mut c_char),
+    delete_program: unsafe extern "system" fn(GLuint),
+    use_program: unsafe extern "system" fn(GLuint),
+    get_uniform_location: unsafe extern "system" fn(GLuint, *const c_char) -> GLint,
+    get_attrib_location: unsafe extern "system" fn(GLuint, *const c_char) -> GLint,
+    uniform_1i: unsafe extern "system" fn(GLint, GLint),
+    uniform_matrix_4fv: unsafe extern "system" fn(GLint, GLsizei, GLboolean, *const GLfloat),
+    gen_buffers: unsafe extern "system" fn(GLsizei, *mut GLuint),
+    delete_buffers: unsafe extern "system" fn(GLsizei, *const GLuint),
+    bind_buffer: unsafe extern "system" fn(GLenum, GLuint),
+    buffer_data: unsafe extern "system" fn(GLenum, GLsizeiptr, *const c_void, GLenum),
+    gen_vertex_arrays: unsafe extern "system" fn(GLsizei, *mut GLuint),
+    delete_vertex_arrays: unsafe extern "system" fn(GLsizei, *const GLuint),
+    bind_vertex_array: unsafe extern "system" fn(GLuint),
+    enable_vertex_attrib_array: unsafe extern "system" fn(GLuint),
+    vertex_attrib_pointer:
+        unsafe extern "system" fn(GLuint, GLint, GLenum, GLboolean, GLsizei, *const c_void),
+    active_texture: unsafe extern "system" fn(GLenum),
+    bind_texture: unsafe extern "system" fn(GLenum, GLuint),
+    gen_textures: unsafe extern "system" fn(GLsizei, *mut GLuint),
+    delete_textures: unsafe extern "system" fn(GLsizei, *const GLuint),
+    tex_parameteri: unsafe extern "system" fn(GLenum, GLenum, GLint),
+    tex_image_2d: unsafe extern "system" fn(
+        GLenum,
+        GLint,
+        GLint,
+        GLsizei,
+        GLsizei,
+        GLint,
+        GLenum,
+        GLenum,
+        *const c_void,
+    ),
+    pixel_storei: unsafe extern "system" fn(GLenum, GLint),
+    blend_equation: unsafe extern "system" fn(GLenum),
+    blend_equation_separate: unsafe extern "system" fn(GLenum, GLenum),
+    blend_func_separate: unsafe extern "system" fn(GLenum, GLenum, GLenum, GLenum),
+    draw_elements_base_vertex:
+        unsafe extern "system" fn(GLenum, GLsizei, GLenum, *const c_void, GLint),
+}
+
+impl Functions {
+    fn load() -> Option<Self> {
+        Some(Functions {
+            create_shader: load_fn("glCreateShader")?,
+            shader_source: load_fn("glShaderSource")?,
+            compile_shader: load_fn("glCompileShader")?,
+            get_shaderiv: load_fn("glGetShaderiv")?,
+            get_shader_info_log: load_fn("glGetShaderInfoLog")?,
+            delete_shader: load_fn("glDeleteShader")?,
+            create_program: load_fn("glCreateProgram")?,
+            attach_shader: load_fn("glAttachShader")?,
+            link_program: load_fn("glLinkProgram")?,
+            get_programiv: load_fn("glGetProgramiv")?,
+            get_program_info_log: load_fn("glGetProgramInfoLog")?,
+            delete_program: load_fn("glDeleteProgram")?,
+            use_program: load_fn("glUseProgram")?,
+            get_uniform_location: load_fn("glGetUniformLocation")?,
+            get_attrib_location: load_fn("glGetAttribLocation")?,
+            uniform_1i: load_fn("glUniform1i")?,
+            uniform_matrix_4fv: load_fn("glUniformMatrix4fv")?,
+            gen_buffers: load_fn("glGenBuffers")?,
+            delete_buffers: load_fn("glDeleteBuffers")?,
+            bind_buffer: load_fn("glBindBuffer")?,
+            buffer_data: load_fn("glBufferData")?,
+            gen_vertex_arrays: load_fn("glGenVertexArrays")?,
+            delete_vertex_arrays: load_fn("glDeleteVertexArrays")?,
+            bind_vertex_array: load_fn("glBindVertexArray")?,
+            enable_vertex_attrib_array: load_fn("glEnableVertexAttribArray")?,
+            vertex_attrib_pointer: load_fn("glVertexAttribPointer")?,
+            active_texture: load_fn("glActiveTexture")?,
+            bind_texture: load_fn("glBindTexture")?,
+            gen_textures: load_fn("glGenTextures")?,
+            delete_textures: load_fn("glDeleteTextures")?,
+            tex_parameteri: load_fn("glTexParameteri")?,
+            tex_image_2d: load_fn("glTexImage2D")?,
+            pixel_storei: load_fn("glPixelStorei")?,
+            blend_equation: load_fn("glBlendEquation")?,
+            blend_equation_separate: load_fn("glBlendEquationSeparate")?,
+            blend_func_separate: load_fn("glBlendFuncSeparate")?,
+            draw_elements_base_vertex: load_fn("glDrawElementsBaseVertex")?,
+        })
+    }
+}
+
+// Of the context the overlay was set up on, see `gl::load`.
+static FUNCTIONS: Mutex<Option<Functions>> = Mutex::new(None);
+
+/// Resolves the GL 3.3 functions from the current context, for `gl::load`.
+pub(crate) fn load_functions() {
+    *FUNCTIONS.lock().unwrap() = Functions::load();
+}
+
+pub(crate) fn unload_functions() {
+    *FUNCTIONS.lock().unwrap() = None;
+}
+
+fn functions() -> Result<Functions> {
+    FUNCTIONS
+        .lock()
+        .unwrap()
+        .ok_or_else(|| anyhow!("Context lacks GL 3.3 functionality"))
+}
+
+const VERTEX_SHADER: &str = "#version 330 core
+uniform mat4 ProjMtx;
+in vec2 Position;
+in vec2 UV;
+in vec4 Color;
+out vec2 Frag_UV;
+out vec4 Frag_Color;
+void main() {
+    Frag_UV = UV;
+    Frag_Color = Color;
+    gl_Position = ProjMtx * vec4(Position.xy, 0, 1);
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 330 core
+uniform sampler2D Texture;
+in vec2 Frag_UV;
+in vec4 Frag_Color;
+out vec4 Out_Color;
+void main() {
+    Out_Color = Frag_Color * texture(Texture, Frag_UV.st);
+}
+";
+
+pub(crate) struct GlRenderer {
+    gl: Gl,
+    gl3: Functions,
+    program: GLuint,
+    loc_texture: GLint,
+    loc_proj_mtx: GLint,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+}
+
+unsafe fn compile_shader(gl3: &Functions, kind: GLenum, source: &str) -> Result<GLuint> {
+    let shader = (gl3.create_shader)(kind);
+    let source = CString::new(source).unwrap();
+    (gl3.shader_source)(shader, 1, &source.as_ptr(), ptr::null());
+    (gl3.compile_shader)(shader);
+
+    let mut status = 0;
+    (gl3.get_shaderiv)(shader, GL_COMPILE_STATUS, &mut status);
+    if status != GL_TRUE as GLint {
+        let mut len = 0;
+        (gl3.get_shaderiv)(shader, GL_INFO_LOG_LENGTH, &mut len);
+        let mut log = vec![0u8; len.max(1) as usize];
+        (gl3.get_shader_info_log)(
+            shader,
+            log.len() as GLsizei,
+            ptr::null_mut(),
+            log.as_mut_ptr() as *mut c_char,
+        );
+        (gl3.delete_shader)(shader);
+
+        return Err(anyhow!(
+            "Failed compiling shader: {}",
+            String::from_utf8_lossy(&log).trim_end_matches('\0')
+        ));
+    }
+
+    Ok(shader)
+}
+
+unsafe fn link_program(gl3: &Functions) -> Result<GLuint> {
+    let vertex = compile_shader(gl3, GL_VERTEX_SHADER, VERTEX_SHADER)?;
+    let fragment = match compile_shader(gl3, GL_FRAGMENT_SHADER, FRAGMENT_SHADER) {
+        Ok(fragment) => fragment,
+        Err(e) => {
+            (gl3.delete_shader)(vertex);
+            return Err(e);
+        }
+    };
+
+    let program = (gl3.create_program)();
+    (gl3.attach_shader)(program, vertex);
+    (gl3.attach_shader)(program, fragment);
+    (gl3.link_program)(program);
+    // Flagged for deletion, they go away together with the program.
+    (gl3.delete_shader)(vertex);
+    (gl3.delete_shader)(fragment);
+
+    let mut status = 0;
+    (gl3.get_programiv)(program, GL_LINK_STATUS, &mut status);
+    if status != GL_TRUE as GLint {
+        let mut len = 0;
+        (gl3.get_programiv)(program, GL_INFO_LOG_LENGTH, &mut len);
+        let mut log = vec![0u8; len.max(1) as usize];
+        (gl3.get_program_info_log)(
+            program,
+            log.len() as GLsizei,
+            ptr::null_mut(),
+            log.as_mut_ptr() as *mut c_char,
+        );
+        (gl3.delete_program)(program);
+
+        return Err(anyhow!(
+            "Failed linking shader program: {}",
+            String::from_utf8_lossy(&log).trim_end_matches('\0')
+        ));
+    }
+
+    Ok(program)
+}
+
+impl GlRenderer {
+    /// Creates the program and buffers in the current context.
+    ///
+    /// Textures are not created here: draw commands reference whatever texture ids ImGui hands
+    /// out, which must be valid (or shared) in this context.
+    pub(crate) fn new() -> Result<Self> {
+        let gl = gl::get().ok_or_else(|| anyhow!("GL functions not loaded"))?;
+        let gl3 = functions()?;
+
+        unsafe {
+            let program = link_program(&gl3)?;
+
+            let name = |s: &str| CString::new(s).unwrap();
+            let loc_texture = (gl3.get_uniform_location)(program, name("Texture").as_ptr());
+            let loc_proj_mtx = (gl3.get_uniform_location)(program, name("ProjMtx").as_ptr());
+            let loc_position = (gl3.get_attrib_location)(program, name("Position").as_ptr());
+            let loc_uv = (gl3.get_attrib_location)(program, name("UV").as_ptr());
+            let loc_color = (gl3.get_attrib_location)(program, name("Color").as_ptr());
+
+            let mut vao = 0;
+            let mut vbo = 0;
+            let mut ebo = 0;
+            (gl3.gen_vertex_arrays)(1, &mut vao);
+            (gl3.gen_buffers)(1, &mut vbo);
+            (gl3.gen_buffers)(1, &mut ebo);
+
+            // The VAO remembers the attribute layout and the element buffer.
+            (gl3.bind_vertex_array)(vao);
+            (gl3.bind_buffer)(GL_ARRAY_BUFFER, vbo);
+            (gl3.bind_buffer)(GL_ELEMENT_ARRAY_BUFFER, ebo);
+
+            let stride = mem::size_of::<DrawVert>() as GLsizei;
+            for (loc, size, kind, normalized, offset) in [
+                (loc_position, 2, GL_FLOAT, GL_FALSE, 0),
+                (loc_uv, 2, GL_FLOAT, GL_FALSE, 8),
+                (loc_color, 4, GL_UNSIGNED_BYTE, GL_TRUE, 16),
+            ] {
+                if loc >= 0 {
+                    (gl3.enable_vertex_attrib_array)(loc as GLuint);
+                    (gl3.vertex_attrib_pointer)(
+                        loc as GLuint,
+                        size,
+                        kind,
+                        normalized,
+                        stride,
+                        offset as *const _,
+                    );
+                }
+            }
+
+            (gl3.bind_vertex_array)(0);
+
+            Ok(GlRenderer {
+                gl,
+                gl3,
+                program,
+                loc_texture,
+                loc_proj_mtx,
+                vao,
+                vbo,
+                ebo,
+            })
+        }
+    }
+
+    /// Draws `draw_data` scaled to a `framebuffer_size` area whose lower left corner is at
+    /// `origin` (GL window coordinates).
+    ///
+    /// `projection` replaces the orthographic projection mapping ImGui's coordinates to the
+    /// area. Clip rects are only meaningful in screen space, so scissoring is off with one.
+    pub(crate) fn render(
+        &self,
+        draw_data: &DrawData,
+        framebuffer_size: [f32; 2],
+        origin: [i32; 2],
+        projection: Option<&[[f32; 4]; 4]>,
+    ) {
+        let [fb_width, fb_height] = framebuffer_size;
+        let [display_width, display_height] = draw_data.display_size;
+        if fb_width <= 0.0 || fb_height <= 0.0 || display_width <= 0.0 || display_height <= 0.0 {
+            return;
+        }
+
+        let scale = [fb_width / display_width, fb_height / display_height];
+        let [left, top] = draw_data.display_pos;
+        let right = left + display_width;
+        let bottom = top + display_height;
+        let ortho = [
+            [2.0 / (right - left), 0.0, 0.0, 0.0],
+            [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+            [0.0, 0.0, -1.0, 0.0],
+            [
+                (right + left) / (left - right),
+                (top + bottom) / (bottom - top),
+                0.0,
+                1.0,
+            ],
+        ];
+        let proj_mtx = projection.unwrap_or(&ortho);
+
+        let (gl, gl3) = (self.gl, self.gl3);
+        let backup = unsafe { StateBackup::save(gl, gl3) };
+
+        unsafe {
+            (gl.enable)(GL_BLEND);
+            (gl3.blend_equation)(GL_FUNC_ADD);
+            (gl3.blend_func_separate)(
+                GL_SRC_ALPHA,
+                GL_ONE_MINUS_SRC_ALPHA,
+                GL_ONE,
+                GL_ONE_MINUS_SRC_ALPHA,
+            );
+            (gl.disable)(GL_CULL_FACE);
+            if config::read(|config| config.srgb_framebuffer) {
+                (gl.enable)(GL_FRAMEBUFFER_SRGB);
+            } else {
+                (gl.disable)(GL_FRAMEBUFFER_SRGB);
+            }
+            // Left as the game set it otherwise, for in-world UIs the scene occludes.
+            if config::read(|config| config.disable_depth_test) {
+                (gl.disable)(GL_DEPTH_TEST);
+            }
+            if projection.is_some() {
+                (gl.disable)(GL_SCISSOR_TEST);
+            } else {
+                (gl.enable)(GL_SCISSOR_TEST);
+            }
+            (gl.viewport)(origin[0], origin[1], fb_width as GLint, fb_height as GLint);
+
+            (gl3.use_program)(self.program);
+            (gl3.uniform_1i)(self.loc_texture, 0);
+            (gl3.uniform_matrix_4fv)(self.loc_proj_mtx, 1, GL_FALSE, proj_mtx[0].as_ptr());
+            (gl3.active_texture)(GL_TEXTURE0);
+            (gl3.bind_vertex_array)(self.vao);
+            (gl3.bind_buffer)(GL_ARRAY_BUFFER, self.vbo);
+
+            for draw_list in draw_data.draw_lists() {
+                let vtx_buffer = draw_list.vtx_buffer();
+                let idx_buffer = draw_list.idx_buffer();
+                (gl3.buffer_data)(
+                    GL_ARRAY_BUFFER,
+                    mem::size_of_val(vtx_buffer) as _,
+                    vtx_buffer.as_ptr() as *const _,
+                    GL_STREAM_DRAW,
+                );
+                (gl3.buffer_data)(
+                    GL_ELEMENT_ARRAY_BUFFER,
+                    mem::size_of_val(idx_buffer) as _,
+                    idx_buffer.as_ptr() as *const _,
+                    GL_STREAM_DRAW,
+                );
+
+                for cmd in draw_list.commands() {
+                    // Raw callbacks are meant for the backend that produced them, skip them.
+                    let DrawCmd::Elements { count, cmd_params } = cmd else {
+                        continue;
+                    };
+
+                    if projection.is_none() {
+                        let [x1, y1, x2, y2] = cmd_params.clip_rect;
+                        let clip = [
+                            (x1 - left) * scale[0],
+                            (y1 - top) * scale[1],
+                            (x2 - left) * scale[0],
+                            (y2 - top) * scale[1],
+                        ];
+                        if clip[0] >= fb_width
+                            || clip[1] >= fb_height
+                            || clip[2] < 0.0
+                            || clip[3] < 0.0
+                        {
+                            continue;
+                        }
+
+                        (gl.scissor)(
+                            origin[0] + clip[0] as GLint,
+                            origin[1] + (fb_height - clip[3]) as GLint,
+                            (clip[2] - clip[0]) as GLint,
+                            (clip[3] - clip[1]) as GLint,
+                        );
+                    }
+
+                    (gl3.bind_texture)(GL_TEXTURE_2D, cmd_params.texture_id.id() as GLuint);
+                    (gl3.draw_elements_base_vertex)(
+                        GL_TRIANGLES,
+                        count as GLsizei,
+                        GL_UNSIGNED_SHORT,
+                        (cmd_params.idx_offset * mem::size_of::<DrawIdx>()) as *const _,
+                        cmd_params.vtx_offset as GLint,
+                    );
+                }
+            }
+
+            backup.restore();
+        }
+    }
+
+    /// Draws `draw_data` into the game's framebuffer, where `imgui_opengl_renderer` would.
+    pub(crate) fn render_to_game(&self, draw_data: &DrawData, projection: Option<&[[f32; 4]; 4]>) {
+        let [width, height] = draw_data.display_size;
+        let [scale_x, scale_y] = draw_data.framebuffer_scale;
+        let (origin_x, origin_y) = gl::draw_origin();
+
+        self.render(
+            draw_data,
+            [width * scale_x, height * scale_y],
+            [origin_x, origin_y],
+            projection,
+        );
+    }
+}
+
+/// The GL state an overlay renderer changes, so the game finds it as it left it.
+///
+/// Taken by `GlRenderer::render` for itself and by `gl_state::prepare` around every renderer, as
+/// `imgui_opengl_renderer` misses some of it (the element buffer, texture units besides the active
+/// one, the depth mask).
+pub(crate) struct StateBackup {
+    gl: Gl,
+    gl3: Functions,
+    program: GLint,
+    /// Of texture unit 0, which the renderers draw with.
+    texture: GLint,
+    active_texture: GLint,
+    array_buffer: GLint,
+    element_array_buffer: GLint,
+    vertex_array: GLint,
+    blend_src_rgb: GLint,
+    blend_dst_rgb: GLint,
+    blend_src_alpha: GLint,
+    blend_dst_alpha: GLint,
+    blend_equation_rgb: GLint,
+    blend_equation_alpha: GLint,
+    viewport: [GLint; 4],
+    scissor_box: [GLint; 4],
+    depth_mask: GLboolean,
+    blend: GLboolean,
+    cull_face: GLboolean,
+    depth_test: GLboolean,
+    stencil_test: GLboolean,
+    scissor_test: GLboolean,
+    framebuffer_srgb: GLboolean,
+}
+
+/// Saves the state of the current context, `None` if it lacks the GL 3.3 functions needed to put
+/// it back.
+pub(crate) fn save_state() -> Option<StateBackup> {
+    let gl = gl::get()?;
+    let gl3 = functions().ok()?;
+
+    Some(unsafe { StateBackup::save(gl, gl3) })
+}
+
+impl StateBackup {
+    unsafe fn save(gl: Gl, gl3: Functions) -> Self {
+        let int = |name| {
+            let mut value = 0;
+            (gl.get_integerv)(name, &mut value);
+            value
+        };
+        let mut viewport = [0; 4];
+        let mut scissor_box = [0; 4];
+        (gl.get_integerv)(GL_VIEWPORT, viewport.as_mut_ptr());
+        (gl.get_integerv)(GL_SCISSOR_BOX, scissor_box.as_mut_ptr());
+        let mut depth_mask = 0;
+        (gl.get_booleanv)(GL_DEPTH_WRITEMASK, &mut depth_mask);
+
+        // Only the active unit's binding can be queried, switch to the one the renderers use.
+        let active_texture = int(GL_ACTIVE_TEXTURE);
+        (gl3.active_texture)(GL_TEXTURE0);
+
+        StateBackup {
+            gl,
+            gl3,
+            program: int(GL_CURRENT_PROGRAM),
+            texture: int(GL_TEXTURE_BINDING_2D),
+            active_texture,
+            array_buffer: int(GL_ARRAY_BUFFER_BINDING),
+            element_array_buffer: int(GL_ELEMENT_ARRAY_BUFFER_BINDING),
+            vertex_array: int(GL_VERTEX_ARRAY_BINDING),
+            blend_src_rgb: int(GL_BLEND_SRC_RGB),
+            blend_dst_rgb: int(GL_BLEND_DST_RGB),
+            blend_src_alpha: int(GL_BLEND_SRC_ALPHA),
+            blend_dst_alpha: int(GL_BLEND_DST_ALPHA),
+            blend_equation_rgb: int(GL_BLEND_EQUATION_RGB),
+            blend_equation_alpha: int(GL_BLEND_EQUATION_ALPHA),
+            viewport,
+            scissor_box,
+            depth_mask,
+            blend: (gl.is_enabled)(GL_BLEND),
+            cull_face: (gl.is_enabled)(GL_CULL_FACE),
+            depth_test: (gl.is_enabled)(GL_DEPTH_TEST),
+            stencil_test: (gl.is_enabled)(GL_STENCIL_TEST),
+            scissor_test: (gl.is_enabled)(GL_SCISSOR_TEST),
+            framebuffer_srgb: (gl.is_enabled)(GL_FRAMEBUFFER_SRGB),
+        }
+    }
+
+    pub(crate) fn restore(&self) {
+        let (gl, gl3) = (self.gl, self.gl3);
+        let set = |cap, enabled| unsafe {
+            if enabled == GL_TRUE {
+                (gl.enable)(cap)
+            } else {
+                (gl.disable)(cap)
+            }
+        };
+
+        unsafe {
+            (gl3.use_program)(self.program as GLuint);
+            (gl3.active_texture)(GL_TEXTURE0);
+            (gl3.bind_texture)(GL_TEXTURE_2D, self.texture as GLuint);
+            (gl3.active_texture)(self.active_texture as GLenum);
+            // The element buffer binding belongs to the vertex array, bind that first.
+            (gl3.bind_vertex_array)(self.vertex_array as GLuint);
+            (gl3.bind_buffer)(GL_ELEMENT_ARRAY_BUFFER, self.element_array_buffer as GLuint);
+            (gl3.bind_buffer)(GL_ARRAY_BUFFER, self.array_buffer as GLuint);
+            (gl3.blend_equation_separate)(
+                self.blend_equation_rgb as GLenum,
+                self.blend_equation_alpha as GLenum,
+            );
+            (gl3.blend_func_separate)(
+                self.blend_src_rgb as GLenum,
+                self.blend_dst_rgb as GLenum,
+                self.blend_src_alpha as GLenum,
+                self.blend_dst_alpha as GLenum,
+            );
+        }
+        set(GL_BLEND, self.blend);
+        set(GL_CULL_FACE, self.cull_face);
+        set(GL_DEPTH_TEST, self.depth_test);
+        set(GL_STENCIL_TEST, self.stencil_test);
+        set(GL_SCISSOR_TEST, self.scissor_test);
+        set(GL_FRAMEBUFFER_SRGB, self.framebuffer_srgb);
+        unsafe {
+            (gl.depth_mask)(self.depth_mask);
+            let [x, y, width, height] = self.viewport;
+            (gl.viewport)(x, y, width, height);
+            let [x, y, width, height] = self.scissor_box;
+            (gl.scissor)(x, y, width, height);
+        }
+    }
+}
+
+/// ImGui's font atlas as a GL texture, for renderers that don't bring their own.
+pub(crate) struct FontTexture {
+    delete_textures: unsafe extern "system" fn(GLsizei, *const GLuint),
+    texture: GLuint,
+}
+
+impl FontTexture {
+    /// Builds the atlas, uploads it to the current context and hands its id to ImGui.
+    pub(crate) fn upload(imgui: &mut Context) -> Result<Self> {
+        let gl = gl::get().ok_or_else(|| anyhow!("GL functions not loaded"))?;
+        let gl3 = functions()?;
+
+        let mut fonts = imgui.fonts();
+        let atlas = fonts.build_rgba32_texture();
+
+        let mut texture = 0;
+        unsafe {
+            let mut prev_texture = 0;
+            let mut prev_row_length = 0;
+            (gl.get_integerv)(GL_TEXTURE_BINDING_2D, &mut prev_texture);
+            (gl.get_integerv)(GL_UNPACK_ROW_LENGTH, &mut prev_row_length);
+
+            (gl3.gen_textures)(1, &mut texture);
+            (gl3.bind_texture)(GL_TEXTURE_2D, texture);
+            (gl3.tex_parameteri)(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR);
+            (gl3.tex_parameteri)(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR);
+            (gl3.pixel_storei)(GL_UNPACK_ROW_LENGTH, 0);
+            (gl3.tex_image_2d)(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA as GLint,
+                atlas.width as GLsizei,
+                atlas.height as GLsizei,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                atlas.data.as_ptr() as *const _,
+            );
+
+            (gl3.pixel_storei)(GL_UNPACK_ROW_LENGTH, prev_row_length);
+            (gl3.bind_texture)(GL_TEXTURE_2D, prev_texture as GLuint);
+        }
+
+        fonts.tex_id = TextureId::from(texture as usize);
+
+        Ok(FontTexture {
+            delete_textures: gl3.delete_textures,
+            texture,
+        })
+    }
+}
+
+impl Drop for FontTexture {
+    fn drop(&mut self) {
+        unsafe { (self.delete_textures)(1, &self.texture) };
+    }
+}
+
+impl Drop for GlRenderer {
+    fn drop(&mut self) {
+        let gl3 = self.gl3;
+
+        unsafe {
+            (gl3.delete_vertex_arrays)(1, &self.vao);
+            (gl3.delete_buffers)(1, &self.vbo);
+            (gl3.delete_buffers)(1, &self.ebo);
+            (gl3.delete_program)(self.program);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod tests {
+    use super::*;
+    use crate::{mock_gl, test_util};
+
+    #[test]
+    fn render_restores_game_state() {
+        let _lock = test_util::lock();
+        test_util::set_up_game_gl_state();
+
+        let renderer = GlRenderer::new().unwrap();
+        let mut imgui = Context::create();
+        let _font_texture = FontTexture::upload(&mut imgui).unwrap();
+        imgui.io_mut().display_size = [800.0, 600.0];
+        let ui = imgui.frame();
+        ui.text("overlay");
+        let draw_data = ui.render();
+
+        let before = mock_gl::snapshot();
+        renderer.render(draw_data, [800.0, 600.0], [0, 0], None);
+        assert_eq!(mock_gl::snapshot(), before);
+        assert!(mock_gl::calls()
+            .iter()
+            .any(|call| call.name == "glDrawElementsBaseVertex" || call.name == "glDrawElements"));
+    }
+
+    #[test]
+    fn gl_tables_go_with_the_overlay() {
+        let _lock = test_util::lock();
+        mock_gl::install();
+        gl::query_info();
+        assert!(GlRenderer::new().is_ok());
+        assert_eq!(gl::version_number(), Some((3, 3)));
+
+        // Dropped with the overlay, the next setup resolves everything from its own context.
+        gl::unload();
+        assert!(gl::get().is_none());
+        assert_eq!(gl::version_number(), None);
+        assert!(GlRenderer::new().is_err());
+
+        mock_gl::install();
+        assert!(GlRenderer::new().is_ok());
+    }
+}
@@ -0,0 +1,180 @@
+//! GL state changed around the overlay draw, restored afterwards.
+//!
+//! Covers the extra steps taken before handing the renderer the frame as well as everything the
+//! renderer itself touches (program, bindings, blend setup, caps, viewport, scissor, depth mask),
+//! without relying on the renderer to put it back. Games with their own shader pipeline tend to
+//! assume nobody changes their bindings between their draws and the swap.
+
+use crate::{
+    gl::{
+        self, GLboolean, GLint, Gl, GL_DEPTH_BUFFER_BIT, GL_DEPTH_TEST, GL_DEPTH_WRITEMASK,
+        GL_DRAW_FRAMEBUFFER, GL_DRAW_FRAMEBUFFER_BINDING, GL_FRAMEBUFFER_SRGB, GL_SCISSOR_BOX,
+        GL_SCISSOR_TEST, GL_TRUE, GL_VIEWPORT,
+    },
+    gl_renderer::{self, StateBackup},
+};
+
+/// Restores the saved state when dropped, so it also happens if rendering panics.
+pub(crate) struct GlStateGuard {
+    gl: Gl,
+    /// All of it, with a GL 3.3 context. Otherwise only the state below can be restored.
+    backup: Option<StateBackup>,
+    depth_test: GLboolean,
+    framebuffer_srgb: GLboolean,
+    depth_mask: GLboolean,
+    scissor_test: GLboolean,
+    scissor_box: [GLint; 4],
+    viewport: [GLint; 4],
+    /// Without `glBindFramebuffer` the game can't have bound another one either.
+    draw_framebuffer: Option<GLint>,
+}
+
+fn set_cap(gl: &Gl, cap: u32, enabled: GLboolean) {
+    unsafe {
+        if enabled == GL_TRUE {
+            (gl.enable)(cap)
+        } else {
+            (gl.disable)(cap)
+        }
+    }
+}
+
+/// Prepares the game's context for drawing an overlay of `framebuffer_size` pixels at the draw
+/// origin. The viewport is set to cover it and scissoring is off, whatever sub-rect the game left
+/// them at (split-screen games present with the last player's viewport). The overlay goes into the
+/// window's framebuffer (or `set_target_framebuffer`'s), not the one the game renders its frames
+/// into for post-processing and may still have bound.
+///
+/// `None` when there is no GL to talk to (e.g. `RendererKind::Null`).
+pub(crate) fn prepare(
+    framebuffer_size: [f32; 2],
+    disable_depth_test: bool,
+    clear_depth: bool,
+    srgb_framebuffer: bool,
+) -> Option<GlStateGuard> {
+    let gl = gl::get()?;
+
+    let mut guard = GlStateGuard {
+        gl,
+        backup: gl_renderer::save_state(),
+        depth_test: 0,
+        framebuffer_srgb: 0,
+        depth_mask: 0,
+        scissor_test: 0,
+        scissor_box: [0; 4],
+        viewport: [0; 4],
+        draw_framebuffer: None,
+    };
+
+    unsafe {
+        guard.depth_test = (gl.is_enabled)(GL_DEPTH_TEST);
+        guard.framebuffer_srgb = (gl.is_enabled)(GL_FRAMEBUFFER_SRGB);
+        guard.scissor_test = (gl.is_enabled)(GL_SCISSOR_TEST);
+        (gl.get_booleanv)(GL_DEPTH_WRITEMASK, &mut guard.depth_mask);
+        (gl.get_integerv)(GL_SCISSOR_BOX, guard.scissor_box.as_mut_ptr());
+        (gl.get_integerv)(GL_VIEWPORT, guard.viewport.as_mut_ptr());
+
+        let (x, y) = gl::draw_origin();
+        let (width, height) = (framebuffer_size[0] as GLint, framebuffer_size[1] as GLint);
+
+        // First, the depth clear is for the framebuffer the overlay goes into.
+        if let Some(bind_framebuffer) = gl.bind_framebuffer {
+            let mut draw_framebuffer = 0;
+            (gl.get_integerv)(GL_DRAW_FRAMEBUFFER_BINDING, &mut draw_framebuffer);
+            guard.draw_framebuffer = Some(draw_framebuffer);
+            bind_framebuffer(GL_DRAW_FRAMEBUFFER, gl::target_framebuffer());
+        }
+        if clear_depth {
+            // Only the part of the depth buffer the overlay covers.
+            (gl.enable)(GL_SCISSOR_TEST);
+            (gl.scissor)(x, y, width, height);
+            (gl.depth_mask)(GL_TRUE);
+            (gl.clear)(GL_DEPTH_BUFFER_BIT);
+        }
+
+        (gl.viewport)(x, y, width, height);
+        (gl.disable)(GL_SCISSOR_TEST);
+
+        if disable_depth_test {
+            (gl.disable)(GL_DEPTH_TEST);
+        }
+        if srgb_framebuffer {
+            (gl.enable)(GL_FRAMEBUFFER_SRGB);
+        }
+    }
+
+    Some(guard)
+}
+
+impl Drop for GlStateGuard {
+    fn drop(&mut self) {
+        let gl = self.gl;
+
+        // Not part of the renderer's state backup, it never binds framebuffers.
+        if let (Some(bind_framebuffer), Some(draw_framebuffer)) =
+            (gl.bind_framebuffer, self.draw_framebuffer)
+        {
+            unsafe { bind_framebuffer(GL_DRAW_FRAMEBUFFER, draw_framebuffer as u32) };
+        }
+
+        if let Some(backup) = &self.backup {
+            return backup.restore();
+        }
+
+        set_cap(&gl, GL_DEPTH_TEST, self.depth_test);
+        set_cap(&gl, GL_FRAMEBUFFER_SRGB, self.framebuffer_srgb);
+        set_cap(&gl, GL_SCISSOR_TEST, self.scissor_test);
+
+        unsafe {
+            (gl.depth_mask)(self.depth_mask);
+            let [x, y, width, height] = self.scissor_box;
+            (gl.scissor)(x, y, width, height);
+            let [x, y, width, height] = self.viewport;
+            (gl.viewport)(x, y, width, height);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod tests {
+    use super::*;
+    use crate::{mock_gl, test_util};
+
+    #[test]
+    fn prepare_restores_game_state() {
+        let _lock = test_util::lock();
+        test_util::set_up_game_gl_state();
+        let before = mock_gl::snapshot();
+
+        let guard = prepare([800.0, 600.0], true, true, true);
+        assert!(guard.is_some());
+
+        let during = mock_gl::snapshot();
+        assert_eq!(during.integers[&GL_VIEWPORT], [0, 0, 800, 600]);
+        assert_eq!(during.integers[&GL_DRAW_FRAMEBUFFER_BINDING][0], 0);
+        assert!(!during.enabled.contains(&GL_DEPTH_TEST));
+        assert!(!during.enabled.contains(&GL_SCISSOR_TEST));
+        assert!(during.enabled.contains(&GL_FRAMEBUFFER_SRGB));
+
+        drop(guard);
+        assert_eq!(mock_gl::snapshot(), before);
+    }
+
+    #[test]
+    fn depth_cleared_in_target_framebuffer() {
+        let _lock = test_util::lock();
+        test_util::set_up_game_gl_state();
+        gl::set_target_framebuffer(3);
+
+        drop(prepare([800.0, 600.0], true, true, false));
+        gl::set_target_framebuffer(0);
+
+        let calls = mock_gl::calls();
+        let position = |name| calls.iter().position(|call| call.name == name).unwrap();
+        assert_eq!(
+            calls[position("glBindFramebuffer")].args,
+            [GL_DRAW_FRAMEBUFFER as i64, 3]
+        );
+        assert!(position("glBindFramebuffer") < position("glClear"));
+    }
+}
@@ -0,0 +1,65 @@
+//! Persisting ImGui's layout (its `.ini` data) through user callbacks instead of a file.
+//!
+//! For a plain file, `HookConfig::ini_path` is enough. The callbacks are for storage ImGui can't
+//! reach on its own (registry, a game's save system, the network); when either is set, ImGui
+//! does not touch the filesystem.
+
+use imgui::Context;
+use std::sync::Mutex;
+
+use crate::config;
+
+type LoadFn = Box<dyn FnMut() -> Option<String> + Send>;
+type SaveFn = Box<dyn FnMut(&str) + Send>;
+
+static LOAD: Mutex<Option<LoadFn>> = Mutex::new(None);
+static SAVE: Mutex<Option<SaveFn>> = Mutex::new(None);
+
+/// Provides the layout to start with.
+///
+/// Called once on the render thread while the overlay initializes, before the first frame. Has
+/// no effect when set afterwards. Returning `None` starts with the default layout.
+pub fn set_ini_load(f: impl FnMut() -> Option<String> + Send + 'static) {
+    *LOAD.lock().unwrap() = Some(Box::new(f));
+}
+
+/// Receives the layout whenever ImGui wants it saved.
+///
+/// Called on the render thread right after a frame has been rendered. ImGui throttles this, a
+/// changed layout is reported at most every `io.ini_saving_rate` seconds (5 by default), so do
+/// not expect a call for the final change before the game exits.
+pub fn set_ini_save(f: impl FnMut(&str) + Send + 'static) {
+    *SAVE.lock().unwrap() = Some(Box::new(f));
+}
+
+/// Sets up persistence on a freshly created context.
+pub(crate) fn init(imgui: &mut Context) {
+    let mut load = LOAD.lock().unwrap();
+    let use_callbacks = load.is_some() || SAVE.lock().unwrap().is_some();
+
+    if use_callbacks {
+        // Without a filename ImGui leaves saving to us through `want_save_ini_settings`.
+        imgui.set_ini_filename(None);
+    } else {
+        imgui.set_ini_filename(config::read(|config| config.ini_path.clone()));
+    }
+
+    if let Some(data) = load.as_mut().and_then(|load| load()) {
+        imgui.load_ini_settings(&data);
+    }
+}
+
+/// Hands the layout to the save callback if ImGui asked for it during the last frame.
+pub(crate) fn save_if_requested(imgui: &mut Context) {
+    if !imgui.io().want_save_ini_settings {
+        return;
+    }
+
+    if let Some(save) = SAVE.lock().unwrap().as_mut() {
+        let mut data = String::new();
+        imgui.save_ini_settings(&mut data);
+        save(&data);
+    }
+
+    imgui.io_mut().want_save_ini_settings = false;
+}
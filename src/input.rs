@@ -0,0 +1,1391 @@
+use anyhow::{anyhow, Result};
+use imgui::{Io, Key};
+use std::{
+    collections::VecDeque,
+    mem, ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicIsize, AtomicU16, AtomicU32, AtomicU8, Ordering},
+        Mutex,
+    },
+};
+use windows::Win32::{
+    Foundation::{GetLastError, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Graphics::Gdi::{PtInRect, ScreenToClient},
+    UI::{
+        Input::{
+            GetRawInputData,
+            KeyboardAndMouse::{
+                GetAsyncKeyState, GetKeyState, TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT,
+                VIRTUAL_KEY, VK_ADD, VK_BACK, VK_CONTROL, VK_DECIMAL, VK_DELETE, VK_DIVIDE,
+                VK_DOWN, VK_END, VK_ESCAPE, VK_F4, VK_HOME, VK_INSERT, VK_LBUTTON, VK_LEFT,
+                VK_LWIN, VK_MBUTTON, VK_MENU, VK_MULTIPLY, VK_NEXT, VK_NUMPAD0, VK_NUMPAD9,
+                VK_PACKET, VK_PRIOR, VK_RBUTTON, VK_RETURN, VK_RIGHT, VK_RWIN, VK_SHIFT, VK_SPACE,
+                VK_SUBTRACT, VK_TAB, VK_UP, VK_XBUTTON1, VK_XBUTTON2,
+            },
+            HRAWINPUT, RAWINPUT, RAWINPUTHEADER, RID_INPUT,
+        },
+        WindowsAndMessaging::{
+            CallNextHookEx, CallWindowProcW, GetClientRect, GetCursorPos, GetForegroundWindow,
+            GetWindowLongPtrW, GetWindowThreadProcessId, IsWindow, SetWindowLongPtrW,
+            SetWindowsHookExW, UnhookWindowsHookEx, GWLP_WNDPROC, HHOOK, HTCLIENT, MSG, PM_REMOVE,
+            SIZE_MAXIMIZED, SIZE_RESTORED, WA_INACTIVE, WHEEL_DELTA, WH_GETMESSAGE, WM_ACTIVATE,
+            WM_CHAR, WM_DEADCHAR, WM_DPICHANGED, WM_INPUT, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS,
+            WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN,
+            WM_MBUTTONUP, WM_MOUSEFIRST, WM_MOUSEHWHEEL, WM_MOUSELAST, WM_MOUSELEAVE, WM_MOUSEMOVE,
+            WM_MOUSEWHEEL, WM_NCHITTEST, WM_NCLBUTTONDBLCLK, WM_NCLBUTTONDOWN, WM_NCLBUTTONUP,
+            WM_NCMBUTTONDBLCLK, WM_NCMBUTTONDOWN, WM_NCMBUTTONUP, WM_NCRBUTTONDBLCLK,
+            WM_NCRBUTTONDOWN, WM_NCRBUTTONUP, WM_NCXBUTTONDBLCLK, WM_NCXBUTTONDOWN, WM_NCXBUTTONUP,
+            WM_NULL, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR, WM_SETFOCUS,
+            WM_SIZE, WM_SYSCHAR, WM_SYSDEADCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDBLCLK,
+            WM_XBUTTONDOWN, WM_XBUTTONUP, WNDPROC,
+        },
+    },
+};
+
+use crate::{
+    config, cursor, eject,
+    error::HookError,
+    lifecycle::{self, HookEvent},
+    unwind, viewport,
+};
+
+/// How the overlay learns about keyboard and mouse input, see `HookConfig::input_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputMode {
+    /// Subclass the game window and read its messages. Full fidelity.
+    #[default]
+    WndProc,
+    /// Hook the messages the game thread retrieves for its window (`WH_GETMESSAGE`) instead of
+    /// subclassing it.
+    ///
+    /// For games that replace their window procedure again, undoing the subclass. Only sees
+    /// posted messages: input, but not sent ones like `WM_SIZE`, `WM_ACTIVATE` or
+    /// `WM_SETCURSOR` (no cursor shapes), and input the game reads otherwise, e.g. with
+    /// `PeekMessage` without removing it, is missed.
+    MessageHook,
+    /// Leave the game window alone and poll `GetAsyncKeyState`/`GetCursorPos` once per frame.
+    ///
+    /// For hosts where subclassing the window is risky (anti-cheat). Lower fidelity: no text
+    /// input (there are no `WM_CHAR`s to read), no mouse wheel, key presses and clicks shorter
+    /// than a frame are missed, and the game still receives all input, ALT+F4 included.
+    Polling,
+}
+
+/// Which mouse clicks and wheel turns reach the game besides the overlay, see
+/// `HookConfig::mouse_forwarding`. Doesn't apply to `InputMode::Polling`. Keyboard input is kept
+/// from the game while ImGui has keyboard focus, regardless of this.
+///
+/// Mouse movement is always forwarded, so the game's cursor stays where the real one is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MouseForwarding {
+    /// The game gets every click, also those on overlay widgets.
+    All,
+    /// Clicks on the overlay stay there, clicks elsewhere reach the game ("click-through except on
+    /// widgets"). Decided by ImGui's `want_capture_mouse`, which also covers drags that started on
+    /// a widget and left it.
+    #[default]
+    OutsideUi,
+    /// The game gets no clicks at all while the overlay is up (modal).
+    None,
+}
+
+// The game's window procedure, as returned by `SetWindowLongPtrW`.
+static ORIG_WNDPROC: AtomicIsize = AtomicIsize::new(0);
+// Set by every message through `wndproc_hook`, cleared by `check_wndproc`.
+static WNDPROC_USED: AtomicBool = AtomicBool::new(false);
+// The `WH_GETMESSAGE` hook and the window whose messages it reads, see `InputMode::MessageHook`.
+static MESSAGE_HOOK: AtomicIsize = AtomicIsize::new(0);
+static HOOKED_WINDOW: AtomicIsize = AtomicIsize::new(0);
+// Window to read input from instead of the device context's, 0 for none. See `set_input_window`.
+static INPUT_WINDOW: AtomicIsize = AtomicIsize::new(0);
+// Key state as of the last poll, indexed by virtual key code.
+static POLLED_KEYS: Mutex<[bool; 256]> = Mutex::new([false; 256]);
+// ImGui's capture flags as of the last frame. The window procedure decides with these what the
+// game gets, it can't look at the ImGui context while a frame is being built.
+static WANT_CAPTURE_MOUSE: AtomicBool = AtomicBool::new(false);
+static WANT_CAPTURE_KEYBOARD: AtomicBool = AtomicBool::new(false);
+
+// Whether the overlay is drawn (flipped by the toggle key) and whether it is in passthrough mode,
+// in one atomic so `set_interactive` changes both at once.
+static OVERLAY_MODE: AtomicU8 = AtomicU8::new(VISIBLE);
+const VISIBLE: u8 = 1 << 0;
+const PASSTHROUGH: u8 = 1 << 1;
+static TOGGLE_KEY: AtomicU16 = AtomicU16::new(VK_INSERT.0);
+// 0 when ejecting by key is off.
+static EJECT_KEY: AtomicU16 = AtomicU16::new(VK_END.0);
+
+// Off by default, see `set_raw_input_fallback`.
+static RAW_INPUT_FALLBACK: AtomicBool = AtomicBool::new(false);
+// Set by the first relative raw mouse motion, from then on `WM_MOUSEMOVE` is ignored.
+static RAW_MOUSE_SEEN: AtomicBool = AtomicBool::new(false);
+// Whether a `WM_MOUSELEAVE` was asked for since the last one, `TrackMouseEvent` only sends one.
+static TRACKING_MOUSE: AtomicBool = AtomicBool::new(false);
+// `SIZE_*` of the last `WM_SIZE`, windows start out restored.
+static SIZE_KIND: AtomicU32 = AtomicU32::new(SIZE_RESTORED);
+
+// A window message waiting for the next frame, see `process_messages`.
+#[derive(Clone, Copy)]
+struct Message {
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+}
+
+// Bounds the queue when the game stops presenting for a while, the oldest messages go first.
+const MAX_QUEUED_MESSAGES: usize = 4096;
+
+static MESSAGES: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
+
+// High surrogate of a character split over two messages, 0 while none is pending.
+static PENDING_SURROGATE: AtomicU16 = AtomicU16::new(0);
+// Character of the last keypad key press while its `WM_CHAR` is outstanding, 0 for none.
+static PENDING_KEYPAD_CHAR: AtomicU32 = AtomicU32::new(0);
+
+// Typed characters wait here until the next frame picks them up, see `flush_chars`.
+struct CharQueue {
+    chars: VecDeque<char>,
+    dropped: usize,
+}
+
+static CHAR_QUEUE: Mutex<CharQueue> = Mutex::new(CharQueue {
+    chars: VecDeque::new(),
+    dropped: 0,
+});
+
+// Both take the whole pointer-sized message parameter, of which only the low 32 bits carry data.
+fn loword(l: usize) -> u16 {
+    (l & 0xffff) as u16
+}
+
+fn hiword(l: usize) -> u16 {
+    ((l >> 16) & 0xffff) as u16
+}
+
+// Mouse coordinates are signed, they go negative while the pointer is captured and dragged
+// past the left or top edge of the client area.
+fn get_x_lparam(lparam: LPARAM) -> i16 {
+    loword(lparam.0 as usize) as i16
+}
+
+fn get_y_lparam(lparam: LPARAM) -> i16 {
+    hiword(lparam.0 as usize) as i16
+}
+
+fn get_wheel_delta_wparam(wparam: WPARAM) -> i16 {
+    hiword(wparam.0) as i16
+}
+
+fn get_xbutton_wparam(wparam: WPARAM) -> u16 {
+    hiword(wparam.0)
+}
+
+// Which X button a `WM_XBUTTON*` message is about, see `get_xbutton_wparam`.
+const XBUTTON1: u16 = 0x0001;
+const XBUTTON2: u16 = 0x0002;
+
+/// ImGui's index for the X button of a `WM_XBUTTON*` message, `None` for unknown buttons.
+fn xbutton_index(wparam: WPARAM) -> Option<usize> {
+    match get_xbutton_wparam(wparam) {
+        XBUTTON1 => Some(3),
+        XBUTTON2 => Some(4),
+        _ => None,
+    }
+}
+
+// Bit 24 of a key message's lparam, set for the extended keys (right-hand CTRL/ALT, keypad Enter).
+const KF_EXTENDED_BIT: isize = 1 << 24;
+// Bit 29 of a key message's lparam, set while ALT is held.
+const KF_ALTDOWN_BIT: isize = 1 << 29;
+// Bit 30 of a key message's lparam, set when the key was already down (auto-repeat).
+const KF_REPEAT_BIT: isize = 1 << 30;
+
+/// Sets the key that shows and hides the overlay, `VK_INSERT` by default.
+///
+/// While hidden the overlay isn't drawn and all input reaches the game.
+pub fn set_toggle_key(vk: VIRTUAL_KEY) {
+    TOGGLE_KEY.store(vk.0, Ordering::Relaxed);
+}
+
+/// Sets the key that unhooks the overlay and unloads the DLL from the game, `VK_END` by default.
+/// `None` turns ejecting by key off.
+///
+/// Only works while the overlay is shown and not typed into, END moves the cursor in text
+/// fields.
+pub fn set_eject_key(vk: Option<VIRTUAL_KEY>) {
+    EJECT_KEY.store(vk.map_or(0, |vk| vk.0), Ordering::Relaxed);
+}
+
+/// Moves ImGui's mouse with the relative motion from raw input (`WM_INPUT`) instead of
+/// `WM_MOUSEMOVE`, off by default.
+///
+/// For games that read the mouse through raw input and keep recentering the hidden cursor, where
+/// the cursor position is useless. Takes effect once the game receives raw mouse motion,
+/// `WM_MOUSEMOVE` keeps working until then.
+pub fn set_raw_input_fallback(enabled: bool) {
+    RAW_INPUT_FALLBACK.store(enabled, Ordering::Relaxed);
+    RAW_MOUSE_SEEN.store(false, Ordering::Relaxed);
+}
+
+/// Reads input from `hwnd` instead of the window the game presents to, for games that give a
+/// child window the keyboard. Sizing still follows the presented window; mouse positions are taken
+/// as `hwnd` reports them, so it should cover that window's client area. Moves over with the next
+/// frame.
+///
+/// Fails with [`HookError::InvalidWindow`] if `hwnd` isn't a window.
+pub fn set_input_window(hwnd: HWND) -> Result<()> {
+    if !unsafe { IsWindow(hwnd) }.as_bool() {
+        return Err(HookError::InvalidWindow(hwnd.0).into());
+    }
+
+    INPUT_WINDOW.store(hwnd.0, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Goes back to reading input from the window the game presents to.
+pub fn clear_input_window() {
+    INPUT_WINDOW.store(0, Ordering::Relaxed);
+}
+
+/// The window set with [`set_input_window`], if any.
+pub(crate) fn input_window() -> Option<HWND> {
+    match INPUT_WINDOW.load(Ordering::Relaxed) {
+        0 => None,
+        hwnd => Some(HWND(hwnd)),
+    }
+}
+
+/// Whether the overlay is shown, see [`set_toggle_key`].
+pub fn overlay_visible() -> bool {
+    OVERLAY_MODE.load(Ordering::Relaxed) & VISIBLE != 0
+}
+
+/// Makes the overlay display only: it keeps being drawn, but ignores the mouse and keyboard,
+/// never keeps input from the game and leaves the cursor alone. For watermarks and ESP-style
+/// overlays. Off by default.
+///
+/// The toggle key keeps working.
+pub fn set_passthrough(enabled: bool) {
+    if enabled {
+        OVERLAY_MODE.fetch_or(PASSTHROUGH, Ordering::Relaxed);
+    } else {
+        OVERLAY_MODE.fetch_and(!PASSTHROUGH, Ordering::Relaxed);
+    }
+}
+
+/// Shows the overlay, taking input (`true`) or display only (`false`, see [`set_passthrough`]).
+///
+/// Both change at once: a window message arriving in between sees either the old mode or the new
+/// one, so none is kept from the game after the overlay became display only.
+pub fn set_interactive(interactive: bool) {
+    let mode = if interactive {
+        VISIBLE
+    } else {
+        VISIBLE | PASSTHROUGH
+    };
+    OVERLAY_MODE.store(mode, Ordering::Relaxed);
+}
+
+/// Whether the overlay currently reacts to input: shown and not in passthrough mode.
+pub(crate) fn takes_input() -> bool {
+    OVERLAY_MODE.load(Ordering::Relaxed) == VISIBLE
+}
+
+/// Whether the message is the first press of the toggle key. Held keys auto-repeat
+/// `WM_KEYDOWN`s, those don't count.
+fn is_toggle_press(msg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+    msg == WM_KEYDOWN
+        && wparam.0 == TOGGLE_KEY.load(Ordering::Relaxed) as usize
+        && lparam.0 & KF_REPEAT_BIT == 0
+}
+
+/// Whether the message is the first press of the eject key.
+fn is_eject_press(msg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+    let eject_key = EJECT_KEY.load(Ordering::Relaxed);
+    msg == WM_KEYDOWN
+        && eject_key != 0
+        && wparam.0 == eject_key as usize
+        && lparam.0 & KF_REPEAT_BIT == 0
+}
+
+/// Whether the message is the ALT+F4 "close window" chord.
+fn is_close_chord(msg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+    msg == WM_SYSKEYDOWN && wparam.0 == VK_F4.0 as usize && lparam.0 & KF_ALTDOWN_BIT != 0
+}
+
+fn is_key_down(vk: VIRTUAL_KEY) -> bool {
+    let state = unsafe { GetKeyState(vk.0 as i32) };
+    state as u16 & 0x8000 != 0
+}
+
+/// Reads the modifier state as of the key message being handled.
+///
+/// Asking Windows instead of tracking the individual keys keeps both sides of a modifier in sync,
+/// and AltGr (which arrives as a fake left CTRL plus right ALT) can't leave CTRL stuck.
+fn update_modifiers(io: &mut Io) {
+    io.key_ctrl = is_key_down(VK_CONTROL);
+    io.key_shift = is_key_down(VK_SHIFT);
+    io.key_alt = is_key_down(VK_MENU);
+    io.key_super = is_key_down(VK_LWIN) || is_key_down(VK_RWIN);
+}
+
+/// Whether the message is a key press or typed character.
+///
+/// Key releases are left out on purpose: they always reach the game, so keys held down when ImGui
+/// took the keyboard don't stay down for the game.
+fn is_key_press(msg: u32) -> bool {
+    matches!(
+        msg,
+        WM_KEYDOWN | WM_SYSKEYDOWN | WM_CHAR | WM_SYSCHAR | WM_DEADCHAR | WM_SYSDEADCHAR
+    )
+}
+
+/// Whether the message is a mouse button or wheel message (but not plain movement).
+fn is_mouse_click(msg: u32) -> bool {
+    msg != WM_MOUSEMOVE && (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&msg)
+}
+
+/// Whether the message is a mouse button message outside the client area (title bar, borders),
+/// or one the game's hit testing made that.
+fn is_nc_mouse_click(msg: u32) -> bool {
+    matches!(
+        msg,
+        WM_NCLBUTTONDOWN
+            | WM_NCLBUTTONUP
+            | WM_NCLBUTTONDBLCLK
+            | WM_NCRBUTTONDOWN
+            | WM_NCRBUTTONUP
+            | WM_NCRBUTTONDBLCLK
+            | WM_NCMBUTTONDOWN
+            | WM_NCMBUTTONUP
+            | WM_NCMBUTTONDBLCLK
+            | WM_NCXBUTTONDOWN
+            | WM_NCXBUTTONUP
+            | WM_NCXBUTTONDBLCLK
+    )
+}
+
+/// The character of a `VK_PACKET` key message, `None` if it has none attached.
+fn packet_char(wparam: WPARAM) -> Option<char> {
+    match hiword(wparam.0) {
+        0 => None,
+        unit => decode_utf16_unit(unit),
+    }
+}
+
+/// Decodes characters arriving one UTF-16 code unit per message, as `WM_CHAR` delivers them.
+///
+/// Returns `None` after the first half of a surrogate pair, the character comes with the second.
+/// Lone surrogates are dropped.
+fn decode_utf16_unit(unit: u16) -> Option<char> {
+    let high = PENDING_SURROGATE.swap(0, Ordering::Relaxed);
+    if (0xd800..0xdc00).contains(&unit) {
+        PENDING_SURROGATE.store(unit, Ordering::Relaxed);
+        return None;
+    }
+
+    // A pending high surrogate that isn't followed by a low one decodes to an error, skipped.
+    let pair = [high, unit];
+    let units = if high != 0 { &pair[..] } else { &pair[1..] };
+    char::decode_utf16(units.iter().copied())
+        .filter_map(Result::ok)
+        .last()
+}
+
+/// The character a keypad key types with NumLock on. Without NumLock the digits are navigation
+/// keys and arrive as such.
+fn keypad_char(wparam: WPARAM) -> Option<char> {
+    match VIRTUAL_KEY(wparam.0 as u16) {
+        vk if (VK_NUMPAD0.0..=VK_NUMPAD9.0).contains(&vk.0) => {
+            char::from_digit((vk.0 - VK_NUMPAD0.0).into(), 10)
+        }
+        VK_ADD => Some('+'),
+        VK_SUBTRACT => Some('-'),
+        VK_MULTIPLY => Some('*'),
+        VK_DIVIDE => Some('/'),
+        VK_DECIMAL => Some('.'),
+        _ => None,
+    }
+}
+
+/// Types the character of the last keypad press if no `WM_CHAR` came for it. `TranslateMessage`
+/// posts it right behind the key down, so by the next key message it is known whether the game
+/// translates (some don't, and polling never does).
+fn flush_keypad_char() {
+    let pending = PENDING_KEYPAD_CHAR.swap(0, Ordering::Relaxed);
+
+    if let Some(c) = char::from_u32(pending).filter(|&c| c != '\0') {
+        queue_char(c);
+    }
+}
+
+// Windows reports the keypad Enter as `VK_RETURN` with the extended bit set, ImGui gets it as
+// this key past the virtual key range (as the C++ Win32 backend does).
+const VK_KEYPAD_ENTER: VIRTUAL_KEY = VIRTUAL_KEY(VK_RETURN.0 + 256);
+
+const KEY_MAP: [(Key, VIRTUAL_KEY); Key::COUNT] = [
+    (Key::Tab, VK_TAB),
+    (Key::LeftArrow, VK_LEFT),
+    (Key::RightArrow, VK_RIGHT),
+    (Key::UpArrow, VK_UP),
+    (Key::DownArrow, VK_DOWN),
+    (Key::PageUp, VK_PRIOR),
+    (Key::PageDown, VK_NEXT),
+    (Key::Home, VK_HOME),
+    (Key::End, VK_END),
+    (Key::Insert, VK_INSERT),
+    (Key::Delete, VK_DELETE),
+    (Key::Backspace, VK_BACK),
+    (Key::Space, VK_SPACE),
+    (Key::Enter, VK_RETURN),
+    (Key::Escape, VK_ESCAPE),
+    (Key::KeyPadEnter, VK_KEYPAD_ENTER),
+    (Key::A, VIRTUAL_KEY(b'A' as u16)),
+    (Key::C, VIRTUAL_KEY(b'C' as u16)),
+    (Key::V, VIRTUAL_KEY(b'V' as u16)),
+    (Key::X, VIRTUAL_KEY(b'X' as u16)),
+    (Key::Y, VIRTUAL_KEY(b'Y' as u16)),
+    (Key::Z, VIRTUAL_KEY(b'Z' as u16)),
+];
+
+pub(crate) fn init_key_map(io: &mut Io) {
+    for (key, vk) in KEY_MAP {
+        io[key] = vk.0 as u32;
+    }
+}
+
+/// Index into `io.keys_down` for a key message, `None` for keys outside the virtual key range.
+fn key_index(wparam: WPARAM, lparam: LPARAM) -> Option<usize> {
+    if wparam.0 == VK_RETURN.0 as usize && lparam.0 & KF_EXTENDED_BIT != 0 {
+        return Some(VK_KEYPAD_ENTER.0 as usize);
+    }
+    (wparam.0 < 256).then_some(wparam.0)
+}
+
+/// Records a key message in `io.keys_down`. The only place indexing it, keys past its end (its
+/// size has changed between imgui versions) are dropped.
+fn set_key_down(io: &mut Io, wparam: WPARAM, lparam: LPARAM, down: bool) {
+    if let Some(key) = key_index(wparam, lparam).and_then(|index| io.keys_down.get_mut(index)) {
+        *key = down;
+    }
+}
+
+/// Starts reading the input of `hwnd` the way `mode` says.
+pub(crate) fn install(mode: InputMode, hwnd: HWND) -> Result<()> {
+    match mode {
+        InputMode::WndProc => install_wndproc(hwnd),
+        InputMode::MessageHook => install_message_hook(hwnd),
+        InputMode::Polling => Ok(()),
+    }
+}
+
+/// Undoes `install`.
+pub(crate) fn uninstall(mode: InputMode, hwnd: HWND) {
+    match mode {
+        InputMode::WndProc => uninstall_wndproc(hwnd),
+        InputMode::MessageHook => uninstall_message_hook(),
+        InputMode::Polling => {}
+    }
+}
+
+/// Hooks the message retrieval of the thread owning `hwnd`, for the messages posted to `hwnd`.
+fn install_message_hook(hwnd: HWND) -> Result<()> {
+    let thread_id = unsafe { GetWindowThreadProcessId(hwnd, ptr::null_mut()) };
+    if thread_id == 0 {
+        return Err(anyhow!(
+            "Failed GetWindowThreadProcessId, GetLastError: {}",
+            unsafe { GetLastError() }.0
+        ));
+    }
+
+    let hook = unsafe {
+        SetWindowsHookExW(
+            WH_GETMESSAGE,
+            Some(get_message_hook),
+            HINSTANCE(0),
+            thread_id,
+        )
+    }?;
+
+    HOOKED_WINDOW.store(hwnd.0, Ordering::Relaxed);
+    MESSAGE_HOOK.store(hook.0, Ordering::Relaxed);
+
+    Ok(())
+}
+
+fn uninstall_message_hook() {
+    let hook = MESSAGE_HOOK.swap(0, Ordering::Relaxed);
+    HOOKED_WINDOW.store(0, Ordering::Relaxed);
+
+    if hook != 0 && !unsafe { UnhookWindowsHookEx(HHOOK(hook)) }.as_bool() {
+        log_error!(
+            "Failed UnhookWindowsHookEx, GetLastError: {}",
+            unsafe { GetLastError() }.0
+        );
+    }
+}
+
+/// Subclasses the game window so its messages reach ImGui first.
+fn install_wndproc(hwnd: HWND) -> Result<()> {
+    let prev = unsafe { SetWindowLongPtrW(hwnd, GWLP_WNDPROC, wndproc_hook as *const () as isize) };
+
+    if prev == 0 {
+        return Err(anyhow!(
+            "Failed SetWindowLongPtrW, GetLastError: {}",
+            unsafe { GetLastError() }.0
+        ));
+    }
+
+    ORIG_WNDPROC.store(prev, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Subclasses `hwnd` again if the game has replaced its window procedure, dropping ours. Call
+/// every now and then, some games do that on ALT+TAB or mode changes.
+///
+/// Another subclass on top of ours (overlays, recorders) also changes the window procedure, but
+/// passes messages on to us; subclassing over it would have messages go round in circles. So the
+/// subclass only counts as gone when no message reached it since the last check.
+pub(crate) fn check_wndproc(hwnd: HWND) {
+    let used = WNDPROC_USED.swap(false, Ordering::Relaxed);
+    let current = unsafe { GetWindowLongPtrW(hwnd, GWLP_WNDPROC) };
+    if used || current == 0 || current == wndproc_hook as *const () as isize {
+        return;
+    }
+
+    log_info!(
+        "Window procedure of {:#x} was replaced, subclassing it again",
+        hwnd.0
+    );
+    match install_wndproc(hwnd) {
+        Ok(()) => lifecycle::emit(HookEvent::ReHooked),
+        Err(e) => log_error!("Failed installing wndproc hook: {}", e),
+    }
+}
+
+/// Undoes `install_wndproc` on `hwnd`, if the window still exists.
+///
+/// Leaves the window alone when something subclassed it after us, putting our predecessor back
+/// would unhook that too.
+fn uninstall_wndproc(hwnd: HWND) {
+    if !unsafe { IsWindow(hwnd) }.as_bool() {
+        return;
+    }
+
+    if unsafe { GetWindowLongPtrW(hwnd, GWLP_WNDPROC) } != wndproc_hook as *const () as isize {
+        log_info!(
+            "Not restoring window procedure of {:#x}, it was subclassed again",
+            hwnd.0
+        );
+        return;
+    }
+
+    unsafe { SetWindowLongPtrW(hwnd, GWLP_WNDPROC, ORIG_WNDPROC.load(Ordering::Relaxed)) };
+}
+
+fn orig_wndproc() -> WNDPROC {
+    unsafe { mem::transmute::<isize, WNDPROC>(ORIG_WNDPROC.load(Ordering::Relaxed)) }
+}
+
+/// Forgets all input state, for when input starts coming from a different window.
+pub(crate) fn reset(io: &mut Io) {
+    release_all(io);
+
+    *POLLED_KEYS.lock().unwrap() = [false; 256];
+    PENDING_SURROGATE.store(0, Ordering::Relaxed);
+    PENDING_KEYPAD_CHAR.store(0, Ordering::Relaxed);
+    MESSAGES.lock().unwrap().clear();
+    WANT_CAPTURE_MOUSE.store(false, Ordering::Relaxed);
+    WANT_CAPTURE_KEYBOARD.store(false, Ordering::Relaxed);
+    TRACKING_MOUSE.store(false, Ordering::Relaxed);
+    SIZE_KIND.store(SIZE_RESTORED, Ordering::Relaxed);
+    CHAR_QUEUE.lock().unwrap().chars.clear();
+}
+
+/// Tells ImGui whether the game window has the keyboard focus.
+fn set_focused(io: &mut Io, focused: bool) {
+    // imgui-rs doesn't wrap it, `Io` is laid out as `ImGuiIO`.
+    unsafe {
+        imgui::sys::ImGuiIO_AddFocusEvent(io as *mut Io as *mut imgui::sys::ImGuiIO, focused)
+    };
+}
+
+/// Lets go of every key and button and moves the mouse away from the UI.
+fn release_all(io: &mut Io) {
+    // ImGui's "no mouse" position.
+    io.mouse_pos = [-f32::MAX, -f32::MAX];
+    io.mouse_down = [false; 5];
+    io.keys_down.fill(false);
+    io.key_ctrl = false;
+    io.key_shift = false;
+    io.key_alt = false;
+    io.key_super = false;
+}
+
+/// Applies a queued message to ImGui's input state, at the start of a frame. The window procedure
+/// returned long before; whether the game got the message was decided there, by
+/// `imgui_wnd_proc_impl`.
+fn apply_message(io: &mut Io, msg: u32, wparam: WPARAM, lparam: LPARAM) {
+    // Moved to a monitor with different scaling, also while hidden. Both words hold the new DPI.
+    if msg == WM_DPICHANGED {
+        viewport::set_dpi(loword(wparam.0).into());
+        return;
+    }
+
+    if is_toggle_press(msg, wparam, lparam)
+        && OVERLAY_MODE.fetch_xor(VISIBLE, Ordering::Relaxed) & VISIBLE != 0
+    {
+        // Hidden now, nothing held should stay pressed on the invisible UI.
+        release_all(io);
+    }
+    // Focus changes also count while hidden, ImGui drops hover state and held input on focus loss.
+    match msg {
+        WM_SETFOCUS => return set_focused(io, true),
+        WM_KILLFOCUS => {
+            release_all(io);
+            return set_focused(io, false);
+        }
+        // Maximizing or restoring the window, also while hidden: a drag that was in progress
+        // would otherwise continue from the old geometry. The restored-size messages of a border
+        // drag don't change the kind and leave the buttons alone. The new size itself is picked
+        // up by `viewport::apply` before the frame is built.
+        WM_SIZE => {
+            let kind = wparam.0 as u32;
+            if SIZE_KIND.swap(kind, Ordering::Relaxed) != kind
+                && matches!(kind, SIZE_MAXIMIZED | SIZE_RESTORED)
+            {
+                io.mouse_down = [false; 5];
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // The UI isn't there to click on or type into, or isn't meant to be.
+    if !takes_input() {
+        return;
+    }
+
+    // `want_text_input` is last frame's, good enough to tell END typed into a text field.
+    if is_eject_press(msg, wparam, lparam) && !io.want_text_input {
+        eject::request();
+        return;
+    }
+
+    match msg {
+        WM_MOUSEMOVE if RAW_MOUSE_SEEN.load(Ordering::Relaxed) => {}
+        WM_MOUSEMOVE => {
+            let pos = [get_x_lparam(lparam) as f32, get_y_lparam(lparam) as f32];
+            io.mouse_pos = viewport::map_mouse_pos(pos);
+        }
+        // Nothing stays hovered with the pointer outside the window, the next move brings it
+        // back.
+        WM_MOUSELEAVE if !RAW_MOUSE_SEEN.load(Ordering::Relaxed) => {
+            io.mouse_pos = [-f32::MAX, -f32::MAX];
+        }
+        // Queued by `wndproc_hook` with the motion already read, see `raw_mouse_motion`.
+        WM_INPUT if RAW_INPUT_FALLBACK.load(Ordering::Relaxed) => {
+            RAW_MOUSE_SEEN.store(true, Ordering::Relaxed);
+            let scale = viewport::pixel_scale();
+            let delta = [wparam.0 as isize as f32 / scale, lparam.0 as f32 / scale];
+            io.mouse_pos = move_mouse(io, delta);
+        }
+        WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => io.mouse_down[0] = true,
+        WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => io.mouse_down[1] = true,
+        WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => io.mouse_down[2] = true,
+        WM_LBUTTONUP => io.mouse_down[0] = false,
+        WM_RBUTTONUP => io.mouse_down[1] = false,
+        WM_MBUTTONUP => io.mouse_down[2] = false,
+        WM_XBUTTONDOWN | WM_XBUTTONDBLCLK => {
+            if let Some(index) = xbutton_index(wparam) {
+                io.mouse_down[index] = true;
+            }
+        }
+        WM_XBUTTONUP => {
+            if let Some(index) = xbutton_index(wparam) {
+                io.mouse_down[index] = false;
+            }
+        }
+        WM_MOUSEWHEEL => {
+            io.mouse_wheel += get_wheel_delta_wparam(wparam) as f32 / WHEEL_DELTA as f32;
+        }
+        WM_MOUSEHWHEEL => {
+            io.mouse_wheel_h += get_wheel_delta_wparam(wparam) as f32 / WHEEL_DELTA as f32;
+        }
+        // Unicode input from on-screen keyboards and tools (`SendInput` with
+        // `KEYEVENTF_UNICODE`) is a character, not a key. Synthesized messages carry it in the
+        // high word; without it the `WM_CHAR` that `TranslateMessage` produces delivers it.
+        WM_KEYDOWN | WM_SYSKEYDOWN if loword(wparam.0) == VK_PACKET.0 => {
+            if let Some(c) = packet_char(wparam) {
+                queue_char(c);
+            }
+        }
+        WM_KEYUP | WM_SYSKEYUP if loword(wparam.0) == VK_PACKET.0 => {}
+        WM_KEYDOWN | WM_SYSKEYDOWN => {
+            flush_keypad_char();
+            // ALT+keypad codes are composed by Windows, not typed digit by digit.
+            if msg == WM_KEYDOWN {
+                if let Some(c) = keypad_char(wparam) {
+                    PENDING_KEYPAD_CHAR.store(c.into(), Ordering::Relaxed);
+                }
+            }
+            set_key_down(io, wparam, lparam, true);
+            update_modifiers(io);
+        }
+        WM_KEYUP | WM_SYSKEYUP => {
+            flush_keypad_char();
+            set_key_down(io, wparam, lparam, false);
+            update_modifiers(io);
+        }
+        WM_CHAR => {
+            // The game translates its key messages, the keypad's characters come this way too.
+            PENDING_KEYPAD_CHAR.store(0, Ordering::Relaxed);
+            if let Some(c) = decode_utf16_unit(wparam.0 as u16) {
+                queue_char(c);
+            }
+        }
+        // The game lost focus, the key and button ups for whatever is held now go elsewhere.
+        WM_ACTIVATE if loword(wparam.0) as u32 == WA_INACTIVE => {
+            release_all(io);
+            io.mouse_wheel = 0.0;
+            io.mouse_wheel_h = 0.0;
+        }
+        _ => {}
+    }
+}
+
+// `RAWINPUTHEADER::dwType` of mouse input.
+const RIM_TYPEMOUSE: u32 = 0;
+// `RAWMOUSE::usFlags` bit set for absolute coordinates.
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+
+/// `io.mouse_pos` moved by `delta`, within the display. Starts from the middle when ImGui has no
+/// mouse position.
+fn move_mouse(io: &Io, delta: [f32; 2]) -> [f32; 2] {
+    let [width, height] = io.display_size;
+    let [x, y] = if io.mouse_pos[0] == -f32::MAX {
+        [width / 2.0, height / 2.0]
+    } else {
+        io.mouse_pos
+    };
+
+    [
+        (x + delta[0]).clamp(0.0, width),
+        (y + delta[1]).clamp(0.0, height),
+    ]
+}
+
+/// The relative mouse motion a `WM_INPUT` carries, `None` for other devices and for absolute
+/// motion (tablets, remote desktop), which `WM_MOUSEMOVE` already gets right.
+///
+/// Only valid inside the window procedure, the raw input handle is gone afterwards.
+fn raw_mouse_motion(lparam: LPARAM) -> Option<(i32, i32)> {
+    let mut input = mem::MaybeUninit::<RAWINPUT>::uninit();
+    let mut size = mem::size_of::<RAWINPUT>() as u32;
+    let read = unsafe {
+        GetRawInputData(
+            HRAWINPUT(lparam.0),
+            RID_INPUT,
+            input.as_mut_ptr() as *mut _,
+            &mut size,
+            mem::size_of::<RAWINPUTHEADER>() as u32,
+        )
+    };
+    // Failure is `u32::MAX`.
+    if read == u32::MAX || (read as usize) < mem::size_of::<RAWINPUTHEADER>() {
+        return None;
+    }
+
+    let input = unsafe { input.assume_init() };
+    if input.header.dwType != RIM_TYPEMOUSE {
+        return None;
+    }
+    let mouse = unsafe { input.data.mouse };
+    if mouse.usFlags & MOUSE_MOVE_ABSOLUTE != 0 || (mouse.lLastX == 0 && mouse.lLastY == 0) {
+        return None;
+    }
+
+    Some((mouse.lLastX, mouse.lLastY))
+}
+
+fn make_lparam(x: i32, y: i32) -> LPARAM {
+    LPARAM((((y as u16 as u32) << 16) | x as u16 as u32) as isize)
+}
+
+/// Feeds the current keyboard and mouse state to ImGui, for [`InputMode::Polling`].
+///
+/// Changes are turned into the window messages they stand for, so both modes share
+/// `apply_message`.
+pub(crate) fn poll(io: &mut Io, hwnd: HWND) {
+    // Input meant for other windows is none of our business, and keys still held when the game
+    // loses focus are released.
+    let focused = unsafe { GetForegroundWindow() } == hwnd;
+    // There is no `WM_DPICHANGED` to tell when the window moves to another monitor.
+    viewport::update_dpi(hwnd);
+
+    let mut pos = POINT::default();
+    let mut client = RECT::default();
+    if focused
+        && unsafe { GetCursorPos(&mut pos) }.as_bool()
+        && unsafe { ScreenToClient(hwnd, &mut pos) }.as_bool()
+        && unsafe { GetClientRect(hwnd, &mut client) }.as_bool()
+    {
+        // Drags keep following the pointer past the edge, as with mouse capture.
+        let msg = if unsafe { PtInRect(&client, pos) }.as_bool() || io.mouse_down.contains(&true) {
+            WM_MOUSEMOVE
+        } else {
+            WM_MOUSELEAVE
+        };
+        apply_message(io, msg, WPARAM(0), make_lparam(pos.x, pos.y));
+    }
+
+    let mut polled_keys = POLLED_KEYS.lock().unwrap();
+    for (vk, was_down) in polled_keys.iter_mut().enumerate().skip(1) {
+        let down = focused && unsafe { GetAsyncKeyState(vk as i32) } as u16 & 0x8000 != 0;
+        if down == *was_down {
+            continue;
+        }
+        *was_down = down;
+
+        let msg = match (VIRTUAL_KEY(vk as u16), down) {
+            (VK_LBUTTON, true) => WM_LBUTTONDOWN,
+            (VK_LBUTTON, false) => WM_LBUTTONUP,
+            (VK_RBUTTON, true) => WM_RBUTTONDOWN,
+            (VK_RBUTTON, false) => WM_RBUTTONUP,
+            (VK_MBUTTON, true) => WM_MBUTTONDOWN,
+            (VK_MBUTTON, false) => WM_MBUTTONUP,
+            (VK_XBUTTON1 | VK_XBUTTON2, true) => WM_XBUTTONDOWN,
+            (VK_XBUTTON1 | VK_XBUTTON2, false) => WM_XBUTTONUP,
+            (_, true) => WM_KEYDOWN,
+            (_, false) => WM_KEYUP,
+        };
+        let wparam = match VIRTUAL_KEY(vk as u16) {
+            VK_XBUTTON1 => WPARAM((XBUTTON1 as usize) << 16),
+            VK_XBUTTON2 => WPARAM((XBUTTON2 as usize) << 16),
+            _ => WPARAM(vk),
+        };
+        apply_message(io, msg, wparam, LPARAM(0));
+    }
+}
+
+/// Queues a character for the next frame, dropping the oldest one if the queue is full. Control
+/// characters are dropped: ImGui takes backspace, tab, enter and escape as keys, their `WM_CHAR`s
+/// would only end up as garbage in text fields.
+fn queue_char(c: char) {
+    if c.is_control() {
+        return;
+    }
+
+    let limit = config::read(|config| config.max_queued_chars);
+    let mut queue = CHAR_QUEUE.lock().unwrap();
+
+    if limit == 0 {
+        queue.dropped += 1;
+        return;
+    }
+
+    while queue.chars.len() >= limit {
+        queue.chars.pop_front();
+        queue.dropped += 1;
+    }
+    queue.chars.push_back(c);
+}
+
+/// Hands the characters typed since the last frame to ImGui.
+pub(crate) fn flush_chars(io: &mut Io) {
+    let mut queue = CHAR_QUEUE.lock().unwrap();
+
+    if queue.dropped > 0 {
+        log_error!(
+            "Input queue full, dropped {} characters (HookConfig::max_queued_chars = {})",
+            queue.dropped,
+            config::read(|config| config.max_queued_chars)
+        );
+        queue.dropped = 0;
+    }
+
+    for c in queue.chars.drain(..) {
+        io.add_input_character(c);
+    }
+}
+
+/// Queues a message for the next frame. Runs of mouse moves collapse into the latest one.
+fn queue_message(message: Message) {
+    let mut messages = MESSAGES.lock().unwrap();
+
+    if message.msg == WM_MOUSEMOVE {
+        if let Some(last) = messages.back_mut().filter(|last| last.msg == WM_MOUSEMOVE) {
+            *last = message;
+            return;
+        }
+    }
+
+    if messages.len() >= MAX_QUEUED_MESSAGES {
+        messages.pop_front();
+    }
+    messages.push_back(message);
+}
+
+/// Hands the messages received since the last frame to ImGui, call before the frame is built.
+pub(crate) fn process_messages(io: &mut Io) {
+    let messages = mem::take(&mut *MESSAGES.lock().unwrap());
+
+    for message in messages {
+        apply_message(io, message.msg, message.wparam, message.lparam);
+    }
+
+    // Whatever was held when passthrough was turned on is never let go otherwise.
+    if overlay_visible() && !takes_input() {
+        release_all(io);
+    }
+}
+
+/// Remembers what ImGui wants to capture, call after the frame is built.
+pub(crate) fn update_capture(io: &Io) {
+    WANT_CAPTURE_MOUSE.store(io.want_capture_mouse, Ordering::Relaxed);
+    WANT_CAPTURE_KEYBOARD.store(io.want_capture_keyboard, Ordering::Relaxed);
+}
+
+/// Hands a window message to ImGui as if the game window had received it. It is processed with
+/// the next frame.
+///
+/// Nothing is forwarded to the game. Useful for driving the UI without a window, see
+/// `RendererKind::Null`.
+pub fn feed_message(msg: u32, wparam: WPARAM, lparam: LPARAM) {
+    queue_message(Message {
+        msg,
+        wparam,
+        lparam,
+    });
+}
+
+/// ImGui's side of the window procedure: queues a message the game window got for the next frame
+/// (see `apply_message`) and decides whether the game gets it too. `Some` means ImGui handled it
+/// and holds the result to return, `None` forwards it to the game's window procedure.
+fn imgui_wnd_proc_impl(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+    if msg == WM_MOUSELEAVE {
+        TRACKING_MOUSE.store(false, Ordering::Relaxed);
+    } else if msg == WM_MOUSEMOVE && takes_input() && !TRACKING_MOUSE.swap(true, Ordering::Relaxed)
+    {
+        track_mouse_leave(hwnd);
+    }
+
+    if msg == WM_INPUT {
+        // The handle in `lparam` is useless by the time the queue is processed, the motion is
+        // queued instead (x in `wparam`, y in `lparam`).
+        if RAW_INPUT_FALLBACK.load(Ordering::Relaxed) {
+            if let Some((x, y)) = raw_mouse_motion(lparam) {
+                queue_message(Message {
+                    msg,
+                    wparam: WPARAM(x as isize as usize),
+                    lparam: LPARAM(y as isize),
+                });
+            }
+        }
+    } else {
+        queue_message(Message {
+            msg,
+            wparam,
+            lparam,
+        });
+    }
+    let over_ui = WANT_CAPTURE_MOUSE.load(Ordering::Relaxed);
+    let typing = WANT_CAPTURE_KEYBOARD.load(Ordering::Relaxed);
+
+    // ALT+F4 always reaches the game so it can be closed with the overlay open, unless the
+    // consumer explicitly asked for it to be eaten.
+    if is_close_chord(msg, wparam, lparam) {
+        return config::read(|config| config.swallow_close_chord).then_some(LRESULT(0));
+    }
+
+    // A hidden or passthrough overlay leaves the game fully playable.
+    if !takes_input() {
+        return None;
+    }
+
+    // Games set their own cursor here, which would override ImGui's over the overlay.
+    if msg == WM_SETCURSOR && loword(lparam.0 as usize) as u32 == HTCLIENT && cursor::apply_shape()
+    {
+        return Some(LRESULT(1));
+    }
+
+    if typing && is_key_press(msg) {
+        return Some(LRESULT(0));
+    }
+
+    let forwarding = config::read(|config| config.mouse_forwarding);
+
+    // Borderless games tend to answer hit tests with `HTCAPTION` to be dragged around by their
+    // client area, which would make a click on the overlay drag the game window instead. Over the
+    // overlay, the pointer is over a client area; elsewhere the game's borders keep working.
+    if over_ui && forwarding != MouseForwarding::All {
+        if msg == WM_NCHITTEST {
+            return Some(LRESULT(HTCLIENT as isize));
+        }
+        // Hit tests answered before the overlay was under the pointer, and the message hook,
+        // which doesn't see them.
+        if is_nc_mouse_click(msg) {
+            return Some(LRESULT(0));
+        }
+    }
+
+    if is_mouse_click(msg) {
+        let forward = match forwarding {
+            MouseForwarding::All => true,
+            MouseForwarding::OutsideUi => !over_ui,
+            MouseForwarding::None => false,
+        };
+        if !forward {
+            // Handled X button messages return TRUE, unlike the other mouse messages.
+            return Some(match msg {
+                WM_XBUTTONDOWN | WM_XBUTTONUP | WM_XBUTTONDBLCLK => LRESULT(1),
+                _ => LRESULT(0),
+            });
+        }
+    }
+
+    None
+}
+
+/// Asks for a `WM_MOUSELEAVE` once the pointer leaves `hwnd`.
+fn track_mouse_leave(hwnd: HWND) {
+    let mut event = TRACKMOUSEEVENT {
+        cbSize: mem::size_of::<TRACKMOUSEEVENT>() as u32,
+        dwFlags: TME_LEAVE,
+        hwndTrack: hwnd,
+        dwHoverTime: 0,
+    };
+
+    if !unsafe { TrackMouseEvent(&mut event) }.as_bool() {
+        TRACKING_MOUSE.store(false, Ordering::Relaxed);
+        log_error!(
+            "Failed TrackMouseEvent, GetLastError: {}",
+            unsafe { GetLastError() }.0
+        );
+    }
+}
+
+unsafe extern "system" fn wndproc_hook(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    // Until the game's window procedure returns, which a modal loop can take long to.
+    let _guard = eject::HookGuard::enter();
+    WNDPROC_USED.store(true, Ordering::Relaxed);
+    let handled = unwind::catch_panic("handling a window message", || {
+        imgui_wnd_proc_impl(hwnd, msg, wparam, lparam)
+    });
+
+    // A panic forwards the message, the game keeps working without the overlay's input.
+    match handled.flatten() {
+        Some(result) => result,
+        None => CallWindowProcW(orig_wndproc(), hwnd, msg, wparam, lparam),
+    }
+}
+
+/// `WH_GETMESSAGE` hook procedure, sees the messages the game thread takes from its queue.
+unsafe extern "system" fn get_message_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let _guard = eject::HookGuard::enter();
+    // Negative codes are to be passed on untouched. Peeks that leave the message in the queue
+    // would have it seen twice.
+    if code >= 0 && wparam.0 as u32 == PM_REMOVE.0 {
+        let message = &mut *(lparam.0 as *mut MSG);
+        if message.hwnd.0 == HOOKED_WINDOW.load(Ordering::Relaxed)
+            && unwind::catch_panic("handling a window message", || {
+                imgui_wnd_proc_impl(
+                    message.hwnd,
+                    message.message,
+                    message.wParam,
+                    message.lParam,
+                )
+            })
+            .flatten()
+            .is_some()
+        {
+            // Kept from the game the only way a message hook can.
+            message.message = WM_NULL;
+        }
+    }
+
+    CallNextHookEx(HHOOK(0), code, wparam, lparam)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::update_config, test_util};
+    use windows::Win32::UI::WindowsAndMessaging::{SIZE_MAXHIDE, SIZE_MAXSHOW, SIZE_MINIMIZED};
+
+    const ALT_DOWN: LPARAM = LPARAM(KF_ALTDOWN_BIT);
+
+    #[test]
+    fn words_ignore_high_bits_of_64_bit_params() {
+        let param = 0xdead_beef_1234_5678_usize;
+
+        assert_eq!(loword(param), 0x5678);
+        assert_eq!(hiword(param), 0x1234);
+        // Sign-extended negative coordinates, as `LPARAM` carries them.
+        let lparam = make_lparam(-5, -7);
+        assert_eq!(get_x_lparam(LPARAM(lparam.0 | !0xffff_ffff)), -5);
+        assert_eq!(get_y_lparam(LPARAM(lparam.0 | !0xffff_ffff)), -7);
+        assert_eq!(get_wheel_delta_wparam(WPARAM(0xffff_ffff_ff88_0000)), -120);
+    }
+
+    #[test]
+    fn key_indices_stay_within_keys_down() {
+        let _lock = test_util::lock();
+        let mut imgui = imgui::Context::create();
+        let io = imgui.io_mut();
+
+        for vk in 0..256 {
+            assert_eq!(key_index(WPARAM(vk), LPARAM(0)), Some(vk));
+            set_key_down(io, WPARAM(vk), LPARAM(0), true);
+            assert!(io.keys_down[vk]);
+        }
+
+        let extended = LPARAM(KF_EXTENDED_BIT);
+        let keypad_enter = VK_KEYPAD_ENTER.0 as usize;
+        assert_eq!(
+            key_index(WPARAM(VK_RETURN.0 as usize), extended),
+            Some(keypad_enter)
+        );
+        set_key_down(io, WPARAM(VK_RETURN.0 as usize), extended, true);
+        assert!(io.keys_down[keypad_enter]);
+        // Only Enter has a keypad twin.
+        assert_eq!(
+            key_index(WPARAM(VK_HOME.0 as usize), extended),
+            Some(VK_HOME.0 as usize)
+        );
+
+        // Past the virtual key range, dropped instead of indexing out of bounds.
+        assert_eq!(key_index(WPARAM(256), LPARAM(0)), None);
+        assert_eq!(key_index(WPARAM(usize::MAX), LPARAM(0)), None);
+        set_key_down(io, WPARAM(usize::MAX), LPARAM(0), true);
+    }
+
+    #[test]
+    fn close_chord_needs_alt_and_f4() {
+        let f4 = WPARAM(VK_F4.0 as usize);
+
+        assert!(is_close_chord(WM_SYSKEYDOWN, f4, ALT_DOWN));
+        // F4 alone is a plain key down, ALT alone is another key.
+        assert!(!is_close_chord(WM_KEYDOWN, f4, LPARAM(0)));
+        assert!(!is_close_chord(WM_SYSKEYDOWN, f4, LPARAM(0)));
+        assert!(!is_close_chord(
+            WM_SYSKEYDOWN,
+            WPARAM(VK_MENU.0 as usize),
+            ALT_DOWN
+        ));
+        // Releasing F4 doesn't close anything.
+        assert!(!is_close_chord(WM_SYSKEYUP, f4, ALT_DOWN));
+    }
+
+    #[test]
+    fn close_chord_reaches_game_while_overlay_visible() {
+        let _lock = test_util::lock();
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+        WANT_CAPTURE_KEYBOARD.store(true, Ordering::Relaxed);
+        let f4 = WPARAM(VK_F4.0 as usize);
+
+        assert_eq!(
+            imgui_wnd_proc_impl(HWND(0), WM_SYSKEYDOWN, f4, ALT_DOWN),
+            None
+        );
+        // Other keys are still kept from the game while typing into the overlay.
+        assert_eq!(
+            imgui_wnd_proc_impl(HWND(0), WM_SYSKEYDOWN, WPARAM(b'A' as usize), ALT_DOWN),
+            Some(LRESULT(0))
+        );
+
+        update_config(|config| config.swallow_close_chord = true);
+        assert_eq!(
+            imgui_wnd_proc_impl(HWND(0), WM_SYSKEYDOWN, f4, ALT_DOWN),
+            Some(LRESULT(0))
+        );
+
+        WANT_CAPTURE_KEYBOARD.store(false, Ordering::Relaxed);
+        MESSAGES.lock().unwrap().clear();
+    }
+
+    #[test]
+    fn size_ends_drags_on_maximize_and_restore_only() {
+        let _lock = test_util::lock();
+        // Also while hidden.
+        OVERLAY_MODE.store(0, Ordering::Relaxed);
+        SIZE_KIND.store(SIZE_RESTORED, Ordering::Relaxed);
+        let mut imgui = imgui::Context::create();
+        let io = imgui.io_mut();
+
+        for (kind, ends_drags) in [
+            // A border drag, restored all along.
+            (SIZE_RESTORED, false),
+            (SIZE_MAXIMIZED, true),
+            (SIZE_MAXIMIZED, false),
+            (SIZE_RESTORED, true),
+            (SIZE_MAXSHOW, false),
+            (SIZE_MAXHIDE, false),
+            (SIZE_MINIMIZED, false),
+            (SIZE_RESTORED, true),
+        ] {
+            io.display_size = [640.0, 480.0];
+            io.mouse_down = [true; 5];
+
+            apply_message(io, WM_SIZE, WPARAM(kind as usize), make_lparam(1920, 1080));
+
+            // Left to `viewport::apply`.
+            assert_eq!(io.display_size, [640.0, 480.0], "SIZE_* {}", kind);
+            assert_eq!(io.mouse_down, [!ends_drags; 5], "SIZE_* {}", kind);
+        }
+
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+    }
+
+    fn take_queued_chars() -> (String, usize) {
+        let mut queue = CHAR_QUEUE.lock().unwrap();
+        let dropped = mem::take(&mut queue.dropped);
+        (queue.chars.drain(..).collect(), dropped)
+    }
+
+    #[test]
+    fn char_queue_stops_at_max_queued_chars() {
+        let _lock = test_util::lock();
+        take_queued_chars();
+        update_config(|config| config.max_queued_chars = 4);
+
+        for c in "abcdefghij".chars() {
+            queue_char(c);
+        }
+        // The newest characters are kept.
+        assert_eq!(take_queued_chars(), ("ghij".to_owned(), 6));
+
+        update_config(|config| config.max_queued_chars = 0);
+        queue_char('a');
+        assert_eq!(take_queued_chars(), (String::new(), 1));
+    }
+
+    #[test]
+    fn control_chars_are_not_queued() {
+        let _lock = test_util::lock();
+        take_queued_chars();
+
+        for c in [
+            '\u{8}', '\t', '\r', '\n', '\u{1b}', '\u{7f}', '\u{9b}', 'a', ' ', 'ß',
+        ] {
+            queue_char(c);
+        }
+
+        assert_eq!(take_queued_chars(), ("a ß".to_owned(), 0));
+    }
+
+    #[test]
+    fn control_char_messages_are_not_typed() {
+        let _lock = test_util::lock();
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+        PENDING_SURROGATE.store(0, Ordering::Relaxed);
+        take_queued_chars();
+        let mut imgui = imgui::Context::create();
+
+        // Backspace, then CTRL+A, as `TranslateMessage` makes them.
+        for unit in [0x08, 0x01, 'b' as usize] {
+            apply_message(imgui.io_mut(), WM_CHAR, WPARAM(unit), LPARAM(0));
+        }
+
+        assert_eq!(take_queued_chars(), ("b".to_owned(), 0));
+    }
+
+    fn packet(unit: u16) -> WPARAM {
+        WPARAM((unit as usize) << 16 | VK_PACKET.0 as usize)
+    }
+
+    #[test]
+    fn packet_chars_decode_utf16() {
+        let _lock = test_util::lock();
+        PENDING_SURROGATE.store(0, Ordering::Relaxed);
+
+        assert_eq!(packet_char(packet('é' as u16)), Some('é'));
+        // Nothing attached, `WM_CHAR` brings the character.
+        assert_eq!(packet_char(WPARAM(VK_PACKET.0 as usize)), None);
+
+        // U+1F600 as D83D DE00, the character comes with the second half.
+        assert_eq!(packet_char(packet(0xd83d)), None);
+        assert_eq!(packet_char(packet(0xde00)), Some('\u{1f600}'));
+    }
+
+    #[test]
+    fn lone_surrogates_are_dropped() {
+        let _lock = test_util::lock();
+        PENDING_SURROGATE.store(0, Ordering::Relaxed);
+
+        assert_eq!(decode_utf16_unit(0xde00), None);
+        // A high surrogate followed by anything but a low one, the character still counts.
+        assert_eq!(decode_utf16_unit(0xd83d), None);
+        assert_eq!(decode_utf16_unit('a' as u16), Some('a'));
+        // A second high surrogate replaces the first.
+        assert_eq!(decode_utf16_unit(0xd83d), None);
+        assert_eq!(decode_utf16_unit(0xd83d), None);
+        assert_eq!(decode_utf16_unit(0xde00), Some('\u{1f600}'));
+    }
+
+    #[test]
+    fn packet_key_down_types_its_char() {
+        let _lock = test_util::lock();
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+        PENDING_SURROGATE.store(0, Ordering::Relaxed);
+        take_queued_chars();
+        let mut imgui = imgui::Context::create();
+        let io = imgui.io_mut();
+
+        for unit in [0xd83d, 0xde00, 'x' as u16] {
+            apply_message(io, WM_KEYDOWN, packet(unit), LPARAM(0));
+            apply_message(io, WM_KEYUP, packet(unit), LPARAM(0));
+        }
+
+        assert_eq!(take_queued_chars(), ("\u{1f600}x".to_owned(), 0));
+        // Not a key as far as ImGui is concerned.
+        assert!(!io.keys_down.contains(&true));
+    }
+
+    #[test]
+    fn clicks_forwarded_by_mouse_forwarding() {
+        let _lock = test_util::lock();
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+        let send = |msg| imgui_wnd_proc_impl(HWND(0), msg, WPARAM(0), LPARAM(0));
+
+        for (forwarding, over_ui, forwarded) in [
+            (MouseForwarding::All, true, true),
+            (MouseForwarding::All, false, true),
+            (MouseForwarding::OutsideUi, true, false),
+            (MouseForwarding::OutsideUi, false, true),
+            (MouseForwarding::None, true, false),
+            (MouseForwarding::None, false, false),
+        ] {
+            update_config(|config| config.mouse_forwarding = forwarding);
+            WANT_CAPTURE_MOUSE.store(over_ui, Ordering::Relaxed);
+            let expected = |result| (!forwarded).then_some(LRESULT(result));
+
+            assert_eq!(send(WM_LBUTTONDOWN), expected(0), "{:?}", forwarding);
+            assert_eq!(send(WM_MOUSEWHEEL), expected(0), "{:?}", forwarding);
+            assert_eq!(send(WM_XBUTTONUP), expected(1), "{:?}", forwarding);
+            // Movement always reaches the game.
+            assert_eq!(send(WM_MOUSEMOVE), None);
+        }
+
+        // Over the UI, hit tests land on the client area unless everything is forwarded.
+        update_config(|config| config.mouse_forwarding = MouseForwarding::OutsideUi);
+        WANT_CAPTURE_MOUSE.store(true, Ordering::Relaxed);
+        assert_eq!(send(WM_NCHITTEST), Some(LRESULT(HTCLIENT as isize)));
+        WANT_CAPTURE_MOUSE.store(false, Ordering::Relaxed);
+        assert_eq!(send(WM_NCHITTEST), None);
+
+        // Hidden, the overlay keeps nothing from the game.
+        update_config(|config| config.mouse_forwarding = MouseForwarding::None);
+        OVERLAY_MODE.store(0, Ordering::Relaxed);
+        assert_eq!(send(WM_LBUTTONDOWN), None);
+
+        OVERLAY_MODE.store(VISIBLE, Ordering::Relaxed);
+        TRACKING_MOUSE.store(false, Ordering::Relaxed);
+        MESSAGES.lock().unwrap().clear();
+    }
+}
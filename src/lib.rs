@@ -1,41 +1,115 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context as _, Result};
 use detour::static_detour;
 use imgui::Context;
-use imgui_opengl_renderer::Renderer;
+use renderer::OverlayRenderer;
 use std::{
+    cell::Cell,
     ffi::{c_int, c_void, CString},
     mem, ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+    thread,
     time::{Duration, Instant},
 };
 use windows::{
     core::PCSTR,
     Win32::{
-        Foundation::{GetLastError, BOOL, HINSTANCE},
-        Graphics::Gdi::HDC,
+        Foundation::{GetLastError, BOOL, HINSTANCE, HWND, RECT},
+        Graphics::{
+            Gdi::{WindowFromDC, HDC},
+            OpenGL::{wglGetCurrentContext, HGLRC},
+        },
         System::{
-            Console::AllocConsole,
-            LibraryLoader::{GetModuleHandleA, GetProcAddress},
-            SystemServices::DLL_PROCESS_ATTACH,
+            LibraryLoader::{GetModuleHandleA, GetProcAddress, LoadLibraryA},
+            SystemServices::{DLL_PROCESS_ATTACH, DLL_PROCESS_DETACH},
         },
+        UI::WindowsAndMessaging::{GetClientRect, IsIconic},
     },
 };
 
+#[macro_use]
+mod logging;
+
+mod builder;
+mod clipboard;
+mod config;
+mod cursor;
+#[cfg(feature = "debug-draw")]
+mod debug_draw;
+mod eject;
+mod error;
+mod fonts;
+mod gl;
+mod gl_renderer;
+mod gl_state;
+mod ini;
+mod input;
+mod lifecycle;
+mod locate;
+mod metrics;
+#[cfg(feature = "experimental-mirror")]
+mod mirror;
+#[cfg(feature = "mock-gl")]
+pub mod mock_gl;
+#[cfg(feature = "experimental-projection")]
+mod projection;
+mod ready;
+mod render_callback;
+mod renderer;
+mod screenshot;
+mod shared;
+mod style;
+#[cfg(test)]
+mod test_util;
+mod unwind;
+mod viewport;
+mod window_state;
+
+pub use builder::HookBuilder;
+pub use config::{
+    config, set_config, set_overlay_max_fps, set_srgb_framebuffer, update_config, HookConfig,
+};
+pub use error::HookError;
+pub use fonts::{add_font_from_bytes, reload_fonts};
+pub use gl::{gl_extensions, gl_version, set_target_framebuffer};
+pub use ini::{set_ini_load, set_ini_save};
+pub use input::{
+    clear_input_window, feed_message, overlay_visible, set_eject_key, set_input_window,
+    set_interactive, set_passthrough, set_raw_input_fallback, set_toggle_key, InputMode,
+    MouseForwarding,
+};
+pub use lifecycle::{set_lifecycle_callback, HookEvent};
+pub use locate::FunctionLocation;
+pub use logging::{enable_debug_console, free_console, set_console_visible, set_logging};
+pub use metrics::{
+    dropped_frames, frames, last_frame_stats, render_interval, reset_metrics, set_show_metrics,
+    FrameStats,
+};
+#[cfg(feature = "experimental-projection")]
+pub use projection::set_projection;
+pub use ready::{is_overlay_ready, set_on_ready, wait_until_initialized};
+pub use render_callback::{
+    add_render_callback, clear_background_draw, remove_render_callback, set_background_draw,
+    set_render_callback, CallbackId, DemoWindow,
+};
+pub use renderer::{null_renderer_stats, DrawStats, RendererKind};
+pub use screenshot::{request_screenshot, Screenshot};
+pub use shared::{clear_shared_state, get_shared_state, set_shared_state};
+pub use style::{set_style_callback, with_style_mut};
+pub use viewport::{
+    clear_target_viewport, set_target_viewport, set_ui_scale, target_viewport, ui_scale, Rect,
+};
+pub use window_state::{set_initial_window_states, WindowState};
+
 fn gl_get_proc_address(procname: &str) -> *const () {
     // For reference on what we do here: https://github.com/Rebzzel/kiero/blob/master/kiero.cpp#L519
 
-    println!("Proc address: {}", procname);
+    log_debug!("Proc address: {}", procname);
     match CString::new(procname) {
-        Ok(procnamer) => {
-            let aweqawe = gl_loader::get_proc_address(procname) as *const ();
-
-            if aweqawe == ptr::null() {
-                println!("Got a null here buddy");
-
-                loop {}
-            }
-
-            aweqawe
-        }
+        // Null for entry points the context lacks, creating whatever asked for them fails then.
+        Ok(_) => gl_loader::get_proc_address(procname) as *const (),
         // string contains a null byte - it won't match anything.
         Err(_) => ptr::null(),
     }
@@ -43,131 +117,895 @@ fn gl_get_proc_address(procname: &str) -> *const () {
 
 #[no_mangle]
 #[allow(non_snake_case)]
-pub extern "system" fn DllMain(
-    _module: HINSTANCE,
-    call_reason: u32,
-    _reserved: *mut c_void,
-) -> BOOL {
-    if call_reason == DLL_PROCESS_ATTACH {
-        BOOL::from(main().is_ok())
-    } else {
-        BOOL::from(true)
+pub extern "system" fn DllMain(module: HINSTANCE, call_reason: u32, reserved: *mut c_void) -> BOOL {
+    match call_reason {
+        DLL_PROCESS_ATTACH => {
+            eject::set_module(module);
+            BOOL::from(spawn_main())
+        }
+        // A non-null `reserved` means the process is exiting: other threads are gone already and
+        // the game's window and context are being torn down anyway, leave everything alone.
+        DLL_PROCESS_DETACH if reserved.is_null() => {
+            if let Err(e) = shutdown_hook() {
+                log_error!("Failed shutting down hook: {}", e);
+            }
+            BOOL::from(true)
+        }
+        _ => BOOL::from(true),
     }
 }
 
-fn create_debug_console() -> Result<()> {
-    if !unsafe { AllocConsole() }.as_bool() {
-        return Err(anyhow!(
-            "Failed allocating console, GetLastError: {}",
-            unsafe { GetLastError() }.0
-        ));
+/// Runs `main` on a thread of its own, which starts once `DllMain` has returned. Hooking under
+/// the loader lock risks deadlocks, detour walks and patches loaded modules.
+fn spawn_main() -> bool {
+    let spawned = thread::Builder::new()
+        .name("opengl-imgui-hook init".to_owned())
+        .spawn(|| {
+            if let Err(e) = main() {
+                // `{:?}` prints the whole chain of causes.
+                log_error!("Failed initializing hook: {:?}", e);
+            }
+        });
+
+    // Dropping the `JoinHandle` detaches the thread and closes its handle.
+    match spawned {
+        Ok(_) => true,
+        Err(e) => {
+            log_error!("Failed spawning init thread: {}", e);
+            false
+        }
     }
+}
 
-    Ok(())
+/// The loaded `module`, loading it if the game hasn't yet, see `get_module_library`.
+fn get_module(module: &str) -> Result<HINSTANCE, HookError> {
+    let module_cstring =
+        CString::new(module).map_err(|_| HookError::InvalidName(module.to_owned()))?;
+    let module_name = PCSTR(module_cstring.as_ptr() as *mut _);
+
+    match unsafe { GetModuleHandleA(module_name) } {
+        Ok(h_instance) => Ok(h_instance),
+        Err(_) => {
+            log_info!("Module {} isn't loaded yet, loading it", module);
+            unsafe { LoadLibraryA(module_name) }.map_err(|e| HookError::ModuleNotFound {
+                module: module.to_owned(),
+                last_error: error::win32_code(&e),
+            })
+        }
+    }
 }
 
+/// Looks `function` up in `module`, loading the module if the game hasn't yet.
+///
+/// Injected early, the hook can come before the game loads `opengl32.dll`. Loading it ourselves
+/// is harmless for the system DLLs the present functions live in: the game gets the same instance
+/// when it loads it later, and the reference we hold keeps it from being unloaded under the
+/// detour.
 fn get_module_library(
     module: &str,
     function: &str,
-) -> Result<unsafe extern "system" fn() -> isize> {
-    let module_cstring = CString::new(module).expect("module");
-    let function_cstring = CString::new(function).expect("function");
+) -> Result<unsafe extern "system" fn() -> isize, HookError> {
+    let function_cstring =
+        CString::new(function).map_err(|_| HookError::InvalidName(function.to_owned()))?;
 
-    let h_instance = unsafe { GetModuleHandleA(PCSTR(module_cstring.as_ptr() as *mut _)) }?;
+    let h_instance = get_module(module)?;
 
     let func = unsafe { GetProcAddress(h_instance, PCSTR(function_cstring.as_ptr() as *mut _)) };
 
-    match func {
-        Some(func) => Ok(func),
-        None => Err(anyhow!(
-            "Failed GetProcAddress, GetLastError: {}",
-            unsafe { GetLastError() }.0
-        )),
-    }
+    func.ok_or_else(|| HookError::ProcAddressFailed {
+        function: format!("{}!{}", module, function),
+        last_error: unsafe { GetLastError() }.0,
+    })
 }
 
 static_detour! {
   pub static OpenGl32wglSwapBuffers: unsafe extern "system" fn(HDC) -> ();
+  pub static Gdi32SwapBuffers: unsafe extern "system" fn(HDC) -> BOOL;
+  pub static OpenGl32wglSwapLayerBuffers: unsafe extern "system" fn(HDC, u32) -> BOOL;
+}
+
+/// Everything the overlay needs once it is up, created by `ensure_initialized`.
+pub(crate) struct HookState {
+    pub(crate) imgui: Context,
+    pub(crate) renderer: OverlayRenderer,
+    pub(crate) hwnd: HWND,
+    /// The GL context the renderer's objects live in.
+    pub(crate) context: HGLRC,
+    /// The window input is read from, see `set_input_window`.
+    pub(crate) input_hwnd: HWND,
+    pub(crate) input_mode: InputMode,
+    /// When the UI was last built, see `HookConfig::min_render_interval_us`.
+    pub(crate) last_build: Option<Instant>,
+    /// The pixel scale (UI scale times DPI scale) the fonts were rasterized for.
+    pub(crate) font_scale: f32,
+}
+
+// SAFETY: neither the ImGui context nor the renderers' GL objects are tied to the thread that
+// created them, they only need exclusive access, which the mutex around `STATE` provides. GL
+// calls still need the game's context current, so in practice only the render thread uses them.
+unsafe impl Send for HookState {}
+
+static STATE: Mutex<Option<HookState>> = Mutex::new(None);
+
+// Set once presents without a window have been reported, they tend to come every frame.
+static NO_WINDOW_LOGGED: AtomicBool = AtomicBool::new(false);
+// Set once it has been reported that `HookConfig::min_render_interval_us` has no effect.
+static NO_REPLAY_LOGGED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static IN_DETOUR: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the thread as inside a present detour for as long as it lives, so the flag is cleared
+/// on every way out, early returns and unwinding included.
+struct DetourGuard {
+    _hook: eject::HookGuard,
+}
+
+impl DetourGuard {
+    /// `None` if the thread is inside a present detour already.
+    fn enter() -> Option<Self> {
+        if IN_DETOUR.replace(true) {
+            return None;
+        }
+        Some(DetourGuard {
+            _hook: eject::HookGuard::enter(),
+        })
+    }
+}
+
+impl Drop for DetourGuard {
+    fn drop(&mut self) {
+        IN_DETOUR.set(false);
+    }
+}
+
+// Fonts are rasterized again once the UI scale and the one they were built for differ by more
+// than this factor, smaller differences just scale the existing glyphs.
+const FONT_REBUILD_RATIO: f32 = 1.25;
+
+// Frames between checks that the game window is still subclassed, see `input::check_wndproc`.
+const WNDPROC_CHECK_INTERVAL: u64 = 120;
+
+/// Size of the overlay in framebuffer pixels.
+fn framebuffer_size(io: &imgui::Io) -> [f32; 2] {
+    [
+        io.display_size[0] * io.display_framebuffer_scale[0],
+        io.display_size[1] * io.display_framebuffer_scale[1],
+    ]
+}
+
+/// Runs `f`, the overlay's GL work, with the GL state set up for the overlay.
+fn with_overlay_gl_state<R>(framebuffer_size: [f32; 2], f: impl FnOnce() -> R) -> R {
+    let debug_group = if config::read(|config| config.debug_groups) {
+        gl::debug_group(c"opengl-imgui-hook overlay")
+    } else {
+        None
+    };
+    let gl_state = config::read(|config| {
+        gl_state::prepare(
+            framebuffer_size,
+            config.disable_depth_test,
+            config.clear_depth,
+            config.srgb_framebuffer,
+        )
+    });
+
+    let result = f();
+
+    drop(gl_state);
+    drop(debug_group);
+
+    result
+}
+
+/// Returns the overlay state, setting it up on the first call that finds the game ready for it.
+///
+/// Until then every call fails and the next one tries again: the window may not exist or have a
+/// client area yet (e.g. a game starting minimized), or the GL functions may not resolve.
+///
+/// `slot` is the locked `STATE`, so presents entering from several threads at once (apps with a
+/// context per thread) can't set the overlay up twice.
+fn ensure_initialized(slot: &mut Option<HookState>, dc: HDC) -> Result<&mut HookState> {
+    if slot.is_none() {
+        let state = slot.insert(create_state(dc)?);
+        ready::mark_initialized();
+        lifecycle::emit(HookEvent::OverlayInitialized { hwnd: state.hwnd });
+    }
+
+    Ok(slot.as_mut().unwrap())
+}
+
+fn create_state(dc: HDC) -> Result<HookState> {
+    let renderer_kind = config::read(|config| config.renderer);
+    let hwnd = unsafe { WindowFromDC(dc) };
+
+    // The null renderer never draws, so it runs without a window (see `run_headless_frame`).
+    if renderer_kind != RendererKind::Null {
+        if hwnd.0 == 0 {
+            return Err(anyhow!("No window for the device context"));
+        }
+
+        let mut client = RECT::default();
+        if !unsafe { GetClientRect(hwnd, &mut client) }.as_bool() {
+            return Err(anyhow!(
+                "Failed GetClientRect, GetLastError: {}",
+                unsafe { GetLastError() }.0
+            ));
+        }
+        if client.right <= client.left || client.bottom <= client.top {
+            return Err(anyhow!("The window has no client area yet"));
+        }
+    }
+
+    let mut imgui = imgui::Context::create();
+
+    imgui.style_mut().window_title_align = [0.5, 0.5];
+    imgui.io_mut().display_size = viewport::DEFAULT_DISPLAY_SIZE;
+    viewport::update_dpi(hwnd);
+    input::init_key_map(imgui.io_mut());
+    imgui.set_clipboard_backend(clipboard::WindowsClipboard);
+    // Before the renderer, which uploads the atlas.
+    fonts::add_fonts(&mut imgui.fonts(), 1.0);
+
+    // Create the renderer
+    let renderer = OverlayRenderer::new(renderer_kind, &mut imgui)?;
+
+    // Only once nothing can fail anymore, the load and style callbacks are called a single time.
+    ini::init(&mut imgui);
+    style::init(imgui.style_mut());
+
+    let input_mode = config::read(|config| config.input_mode);
+    let input_hwnd = input::input_window().unwrap_or(hwnd);
+    if let Err(e) = input::install(input_mode, input_hwnd) {
+        log_error!("Failed installing input hook: {}", e);
+    }
+
+    Ok(HookState {
+        imgui,
+        renderer,
+        hwnd,
+        context: unsafe { wglGetCurrentContext() },
+        input_hwnd,
+        input_mode,
+        last_build: None,
+        font_scale: 1.0,
+    })
+}
+
+/// Moves over to `hwnd` when the game has replaced its window (some engines create a new one on
+/// every level load), and input to the window it should be read from. The ImGui context and
+/// everything in it is kept.
+fn follow_window(state: &mut HookState, hwnd: HWND) {
+    // No window at all (`run_headless_frame`), or nothing changed.
+    if hwnd.0 != 0 && hwnd != state.hwnd {
+        log_info!(
+            "Game window changed from {:#x} to {:#x}",
+            state.hwnd.0,
+            hwnd.0
+        );
+        viewport::update_dpi(hwnd);
+        state.hwnd = hwnd;
+    }
+
+    let input_hwnd = input::input_window().unwrap_or(state.hwnd);
+    if input_hwnd == state.input_hwnd {
+        return;
+    }
+
+    input::uninstall(state.input_mode, state.input_hwnd);
+    if let Err(e) = input::install(state.input_mode, input_hwnd) {
+        log_error!("Failed installing input hook: {}", e);
+    }
+    input::reset(state.imgui.io_mut());
+
+    state.input_hwnd = input_hwnd;
+}
+
+fn render_frame(dc: HDC) {
+    let hwnd = unsafe { WindowFromDC(dc) };
+    // Off-screen rendering (pbuffers, memory DCs) presents too. There is no window to hook input
+    // on or draw the overlay over, the next present on the game window brings it up. The null
+    // renderer needs no window.
+    if hwnd.0 == 0 && config::read(|config| config.renderer) != RendererKind::Null {
+        if !NO_WINDOW_LOGGED.swap(true, Ordering::Relaxed) {
+            log_info!("Skipping presents on device contexts without a window");
+        }
+        metrics::record_dropped_frame();
+        return;
+    }
+
+    let mut slot = STATE.lock().unwrap();
+    // Some games recreate their context, e.g. on video mode changes. The overlay's GL objects went
+    // with the old one, it is set up again on the new one.
+    let context = unsafe { wglGetCurrentContext() };
+    if let Some(state) = slot.take_if(|state| state.context != context) {
+        log_info!("Game's GL context changed, setting the overlay up again");
+        input::uninstall(state.input_mode, state.input_hwnd);
+        ready::mark_uninitialized();
+        // Deleting the objects now would hit those of the new context that carry the same names.
+        mem::forget(state.renderer);
+    }
+    let state = match ensure_initialized(&mut slot, dc) {
+        Ok(state) => state,
+        Err(e) => {
+            log_error!("Failed initializing overlay, retrying next frame: {}", e);
+            metrics::record_dropped_frame();
+            return;
+        }
+    };
+    follow_window(state, hwnd);
+    if state.input_mode == InputMode::WndProc
+        && metrics::frames().is_multiple_of(WNDPROC_CHECK_INTERVAL)
+    {
+        input::check_wndproc(state.input_hwnd);
+    }
+
+    // Nothing to see, and games keep presenting while minimized.
+    if unsafe { IsIconic(state.hwnd) }.as_bool() {
+        metrics::record_dropped_frame();
+        return;
+    }
+
+    // Follows resizes every frame. An empty client area leaves the renderers nothing to draw to.
+    if !viewport::apply(state.hwnd, state.imgui.io_mut()) {
+        metrics::record_dropped_frame();
+        return;
+    }
+
+    let pixel_scale = viewport::pixel_scale();
+    let ratio = pixel_scale / state.font_scale;
+    if fonts::take_reload_request()
+        || !(1.0 / FONT_REBUILD_RATIO..=FONT_REBUILD_RATIO).contains(&ratio)
+    {
+        match state.renderer.rebuild_fonts(&mut state.imgui, pixel_scale) {
+            Ok(()) => state.font_scale = pixel_scale,
+            Err(e) => log_error!("Failed rebuilding fonts: {}", e),
+        }
+    }
+
+    let imgui = &mut state.imgui;
+    let rendererer = &state.renderer;
+
+    let now = Instant::now();
+    let since_last_build = state.last_build.map(|last| now - last);
+    let min_interval =
+        Duration::from_micros(config::read(|config| config.min_render_interval_us).into());
+    // Too early to rebuild the UI, but the game has drawn a new frame over the old overlay, so
+    // draw the previous draw data again.
+    if since_last_build.is_some_and(|elapsed| elapsed < min_interval) && input::overlay_visible() {
+        if with_overlay_gl_state(framebuffer_size(imgui.io()), || {
+            rendererer.render_last_frame()
+        }) {
+            metrics::record_dropped_frame();
+            return;
+        }
+        if !NO_REPLAY_LOGGED.swap(true, Ordering::Relaxed) {
+            log_info!("Can't draw the last frame again without GL 3.3, rebuilding every frame");
+        }
+    }
+
+    imgui.io_mut().delta_time = since_last_build
+        .map_or(1.0 / 60.0, |elapsed| elapsed.as_secs_f32())
+        .max(f32::MIN_POSITIVE);
+    if let Some(elapsed) = since_last_build {
+        metrics::record_render_interval(elapsed);
+    }
+    state.last_build = Some(now);
+
+    input::process_messages(imgui.io_mut());
+    if state.input_mode == InputMode::Polling {
+        input::poll(imgui.io_mut(), state.hwnd);
+    }
+    input::flush_chars(imgui.io_mut());
+    style::apply(imgui.style_mut());
+    // ImGui's capture flags are those of the last frame until the next one is built.
+    let wants_mouse = input::takes_input() && imgui.io().want_capture_mouse;
+    cursor::update(imgui.io_mut(), wants_mouse);
+    let framebuffer_size = framebuffer_size(imgui.io());
+    let ui = imgui.frame();
+    render_callback::draw(&ui);
+    render_callback::draw_background(&ui);
+    metrics::draw(&ui);
+    window_state::apply();
+    cursor::update_shape(&ui, wants_mouse);
+
+    #[cfg(feature = "debug-draw")]
+    let debug_clip_rects = config::read(|config| config.debug_clip_rects);
+    #[cfg(feature = "debug-draw")]
+    if debug_clip_rects {
+        debug_draw::draw(&ui);
+    }
+
+    // Still built while hidden, so the toggle key keeps being seen and the UI keeps its state.
+    if input::overlay_visible() {
+        with_overlay_gl_state(framebuffer_size, || rendererer.render(ui));
+        metrics::record_frame_stats(imgui.io(), true);
+    } else {
+        drop(ui);
+        metrics::record_frame_stats(imgui.io(), false);
+    }
+    screenshot::capture_if_requested();
+    // Wheel messages only ever add to these, the frame just built has consumed them. ImGui's
+    // `EndFrame` clears them too, but that's an implementation detail of the version in use.
+    imgui.io_mut().mouse_wheel = 0.0;
+    imgui.io_mut().mouse_wheel_h = 0.0;
+    input::update_capture(imgui.io());
+
+    ini::save_if_requested(imgui);
+
+    #[cfg(feature = "experimental-mirror")]
+    mirror::update(config::read(|config| config.mirror_window));
+
+    #[cfg(feature = "debug-draw")]
+    if debug_clip_rects {
+        debug_draw::capture_last_frame();
+    }
 }
 
-static mut INIT: bool = false;
-static mut IMGUI: Option<Context> = None;
-static mut IMGUI_RENDERER: Option<Renderer> = None;
+/// Builds and "renders" one overlay frame without a game, for tests.
+///
+/// Only allowed with [`RendererKind::Null`] configured, as there is no GL context to draw into.
+pub fn run_headless_frame() -> Result<()> {
+    if config::read(|config| config.renderer) != RendererKind::Null {
+        return Err(anyhow!("Headless frames require RendererKind::Null"));
+    }
+
+    render_frame(HDC(0));
+
+    Ok(())
+}
+
+/// What every present detour does: draw the overlay into the back buffer of `dc`, then call
+/// `present` to put the frame on screen.
+fn on_present<R>(dc: HDC, present: impl FnOnce() -> R) -> R {
+    // Swaps issued while rendering the overlay (the mirror window's) come back through here, and
+    // so does `wglSwapBuffers` when `SwapBuffers` forwards to it with both hooked. Either way the
+    // frame is already drawn, so ImGui is left alone.
+    let Some(_guard) = DetourGuard::enter() else {
+        return present();
+    };
+
+    log_debug!("Called wglSwapBuffers");
+    metrics::record_frame();
+
+    if unwind::catch_panic("rendering the overlay", || render_frame(dc)).is_none() {
+        discard_state();
+    }
+
+    let initialized = STATE.lock().unwrap().is_some();
+    log_debug!("INIT: {}", initialized);
+
+    let result = present();
+
+    if initialized {
+        unwind::catch_panic("running the ready callback", ready::mark_ready);
+    }
+    unwind::catch_panic("ejecting", eject::run_if_requested);
+
+    result
+}
 
 #[allow(non_snake_case)]
 pub fn wglSwapBuffers_detour(dc: HDC) -> () {
-    println!("Called wglSwapBuffers");
+    on_present(dc, || unsafe { OpenGl32wglSwapBuffers.call(dc) })
+}
 
-    if !unsafe { INIT } {
-        let mut imgui = imgui::Context::create();
-        imgui.set_ini_filename(None);
+#[allow(non_snake_case)]
+pub fn SwapBuffers_detour(dc: HDC) -> BOOL {
+    on_present(dc, || unsafe { Gdi32SwapBuffers.call(dc) })
+}
 
-        imgui.style_mut().window_title_align = [0.5, 0.5];
-        imgui.io_mut().display_size = [1024.0, 1024.0];
+#[allow(non_snake_case)]
+pub fn wglSwapLayerBuffers_detour(dc: HDC, planes: u32) -> BOOL {
+    // Which layer planes are swapped doesn't matter, the overlay goes into the main plane.
+    on_present(dc, || unsafe {
+        OpenGl32wglSwapLayerBuffers.call(dc, planes)
+    })
+}
 
-        // Init the loader (grabbing the func required)
-        gl_loader::init_gl();
-        // Create the renderer
-        let renderer = imgui_opengl_renderer::Renderer::new(&mut imgui, |s| {
-            gl_loader::get_proc_address(s) as _
-        });
+pub type FnOpenGl32wglSwapBuffers = unsafe extern "system" fn(HDC) -> ();
+pub type FnGdi32SwapBuffers = unsafe extern "system" fn(HDC) -> BOOL;
+pub type FnOpenGl32wglSwapLayerBuffers = unsafe extern "system" fn(HDC, u32) -> BOOL;
+
+/// Which present functions [`init_hook`] detours. `wglSwapLayerBuffers` can be detoured on top
+/// of any of these, see `HookConfig::hook_swap_layer_buffers`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentHook {
+    /// `opengl32.dll!wglSwapBuffers`, what most games call.
+    #[default]
+    WglSwapBuffers,
+    /// `gdi32.dll!SwapBuffers`, for games that present through GDI.
+    SwapBuffers,
+    /// Both, for when it isn't known which one the game uses. A frame is drawn once even when one
+    /// calls the other.
+    Both,
+}
+
+/// Exports [`init_hook_with_targets`] looks the present functions up in, for games that ship a
+/// renamed or relinked OpenGL loader. Defaults to the system DLLs. Modules the game hasn't loaded
+/// yet are loaded by the hook.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HookTargets {
+    /// Module and function detoured for [`PresentHook::WglSwapBuffers`].
+    pub wgl_swap_buffers: (String, String),
+    /// Module and function detoured for [`PresentHook::SwapBuffers`].
+    pub swap_buffers: (String, String),
+    /// Module and function detoured for `HookConfig::hook_swap_layer_buffers`.
+    pub wgl_swap_layer_buffers: (String, String),
+}
+
+impl Default for HookTargets {
+    fn default() -> Self {
+        HookTargets {
+            wgl_swap_buffers: ("opengl32.dll".to_owned(), "wglSwapBuffers".to_owned()),
+            swap_buffers: ("gdi32.dll".to_owned(), "SwapBuffers".to_owned()),
+            wgl_swap_layer_buffers: ("opengl32.dll".to_owned(), "wglSwapLayerBuffers".to_owned()),
+        }
+    }
+}
+
+/// Runs `f` on the overlay's ImGui context, between two frames. `None` before the overlay has
+/// initialized, after [`shutdown_hook`], and when called from inside a present detour.
+///
+/// Frames are built with the context borrowed, so the render callback (and the ready callback,
+/// which also runs in the detour) can't get it and must make do with the `Ui` they are given.
+/// Called from another thread it waits for the frame being built to finish; don't hold it for
+/// long, the game's next frame waits for it in turn.
+pub fn with_context<R>(f: impl FnOnce(&mut Context) -> R) -> Option<R> {
+    if IN_DETOUR.get() {
+        return None;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    state.as_mut().map(|state| f(&mut state.imgui))
+}
+
+/// Address of the trampoline the detour calls to reach the original `wglSwapBuffers`, `None`
+/// until the hook is installed.
+///
+/// For diagnostics only, e.g. to check whether another hook sits in front of ours.
+pub fn original_swap_address() -> Option<usize> {
+    OpenGl32wglSwapBuffers
+        .trampoline()
+        .ok()
+        .map(|trampoline| trampoline as *const () as usize)
+}
+
+/// Calls the original `wglSwapBuffers`, presenting `dc` without drawing the overlay, e.g. for an
+/// extra present of your own. Fails unless the `wglSwapBuffers` detour is enabled, see
+/// [`init_hook`].
+pub fn call_original_swap(dc: HDC) -> Result<()> {
+    if !OpenGl32wglSwapBuffers.is_enabled() {
+        return Err(anyhow!("The wglSwapBuffers detour isn't enabled"));
+    }
+
+    unsafe { OpenGl32wglSwapBuffers.call(dc) };
+    Ok(())
+}
+
+/// Hooks the present functions picked by `hooks`, the overlay comes up with the next frame the
+/// game presents.
+///
+/// For embedding the overlay in your own injected code; the DLL build calls this from `DllMain`
+/// with the default. Calling it again after [`shutdown_hook`] turns the hooks back on.
+///
+/// Fails with the [`HookError`] for the first present function that can't be found
+/// (`ModuleNotFound`, `ProcAddressFailed`, `LocateFailed`), detoured (`DetourInit`) or have its
+/// detour turned on (`DetourEnable`).
+pub fn init_hook(hooks: PresentHook) -> Result<(), HookError> {
+    init_hook_with_targets(hooks, &HookTargets::default())
+}
+
+/// [`init_hook`], looking the present functions up where `targets` says.
+///
+/// The targets only matter the first time a detour is installed; after [`shutdown_hook`] the
+/// hooks come back on the functions they were first installed on.
+pub fn init_hook_with_targets(hooks: PresentHook, targets: &HookTargets) -> Result<(), HookError> {
+    unwind::install_panic_hook();
+
+    match enable_detours(hooks, targets) {
+        Ok(true) => lifecycle::emit(HookEvent::DetourEnabled),
+        Ok(false) => {}
+        Err(e) => {
+            lifecycle::emit(HookEvent::Error(e.to_string()));
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// `module!function`, how errors name a present function.
+fn qualified_name((module, function): &(String, String)) -> String {
+    format!("{}!{}", module, function)
+}
 
-        unsafe { IMGUI = Some(imgui) };
-        unsafe { IMGUI_RENDERER = Some(renderer) };
+/// Installs and enables the detours for `init_hook_with_targets`. Returns whether any of them was
+/// off before.
+fn enable_detours(hooks: PresentHook, targets: &HookTargets) -> Result<bool, HookError> {
+    let mut enabled = false;
+    let init_failed = |target: &(String, String), e: detour::Error| HookError::DetourInit {
+        function: qualified_name(target),
+        reason: e.to_string(),
+    };
+    let enable_failed = |target: &(String, String), e: detour::Error| HookError::DetourEnable {
+        function: qualified_name(target),
+        reason: e.to_string(),
+    };
+
+    // `shutdown_hook` only disables the detours, they stay initialized.
+    if matches!(hooks, PresentHook::WglSwapBuffers | PresentHook::Both) {
+        let target = &targets.wgl_swap_buffers;
+        if OpenGl32wglSwapBuffers.trampoline().is_err() {
+            let (module, function) = target;
+            let x = match config::read(|config| config.wgl_swap_buffers_location.clone()) {
+                Some(location) => locate::find(get_module(module)?, &location).map_err(|e| {
+                    HookError::LocateFailed {
+                        function: qualified_name(target),
+                        reason: format!("{:#}", e),
+                    }
+                })?,
+                None => get_module_library(module, function)?,
+            };
+            let y: FnOpenGl32wglSwapBuffers = unsafe { mem::transmute(x) };
+            unsafe { OpenGl32wglSwapBuffers.initialize(y, wglSwapBuffers_detour) }
+                .map_err(|e| init_failed(target, e))?;
+            log_info!("Initialized wglSwapBuffers detour");
+        }
 
-        unsafe { INIT = true };
+        if !OpenGl32wglSwapBuffers.is_enabled() {
+            unsafe { OpenGl32wglSwapBuffers.enable() }.map_err(|e| enable_failed(target, e))?;
+            log_info!("Enabled wglSwapBuffers detour");
+            enabled = true;
+        }
     }
 
-    if unsafe { INIT } {
-        let imgui = unsafe { &mut IMGUI }.as_mut().unwrap();
-        let ui = imgui.frame();
-        ui.show_demo_window(&mut true);
+    if matches!(hooks, PresentHook::SwapBuffers | PresentHook::Both) {
+        let target = &targets.swap_buffers;
+        if Gdi32SwapBuffers.trampoline().is_err() {
+            let (module, function) = target;
+            let x = get_module_library(module, function)?;
+            let y: FnGdi32SwapBuffers = unsafe { mem::transmute(x) };
+            unsafe { Gdi32SwapBuffers.initialize(y, SwapBuffers_detour) }
+                .map_err(|e| init_failed(target, e))?;
+            log_info!("Initialized SwapBuffers detour");
+        }
 
-        let rendererer = unsafe { &mut IMGUI_RENDERER }.as_mut().unwrap();
-        rendererer.render(ui);
+        if !Gdi32SwapBuffers.is_enabled() {
+            unsafe { Gdi32SwapBuffers.enable() }.map_err(|e| enable_failed(target, e))?;
+            log_info!("Enabled SwapBuffers detour");
+            enabled = true;
+        }
     }
 
-    println!("INIT: {}", unsafe { INIT });
+    if config::read(|config| config.hook_swap_layer_buffers) {
+        let target = &targets.wgl_swap_layer_buffers;
+        if OpenGl32wglSwapLayerBuffers.trampoline().is_err() {
+            let (module, function) = target;
+            let x = get_module_library(module, function)?;
+            let y: FnOpenGl32wglSwapLayerBuffers = unsafe { mem::transmute(x) };
+            unsafe { OpenGl32wglSwapLayerBuffers.initialize(y, wglSwapLayerBuffers_detour) }
+                .map_err(|e| init_failed(target, e))?;
+            log_info!("Initialized wglSwapLayerBuffers detour");
+        }
 
-    /*let mut imgui = imgui::Context::create();
-    imgui.set_ini_filename(None);
+        if !OpenGl32wglSwapLayerBuffers.is_enabled() {
+            unsafe { OpenGl32wglSwapLayerBuffers.enable() }
+                .map_err(|e| enable_failed(target, e))?;
+            log_info!("Enabled wglSwapLayerBuffers detour");
+            enabled = true;
+        }
+    }
 
-    let renderer =
-        imgui_opengl_renderer::Renderer::new(&mut imgui, |s| gl_get_proc_address(s) as _);
+    Ok(enabled)
+}
 
-    let mut last_frame = Instant::now();
+/// Whether any present function is currently detoured, between [`init_hook`] and
+/// [`shutdown_hook`]. The overlay itself comes up later, see [`is_overlay_ready`].
+pub fn is_hooked() -> bool {
+    OpenGl32wglSwapBuffers.is_enabled()
+        || Gdi32SwapBuffers.is_enabled()
+        || OpenGl32wglSwapLayerBuffers.is_enabled()
+}
 
-    loop {
-        let now = Instant::now();
-        let delta = now - last_frame;
-        let delta_s = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
-        last_frame = now;
-        imgui.io_mut().delta_time = delta_s;
+/// Unhooks the present functions and the game window and drops the overlay (ImGui context and
+/// renderer), for when the game keeps running without it. Closes the console if
+/// [`enable_debug_console`] opened one.
+///
+/// Call it on the render thread, but not from the render callback: it waits for the frame being
+/// rendered to finish. Elsewhere the renderer's GL deletes go nowhere without the game's context
+/// current, which leaks the GL objects but is harmless.
+pub fn shutdown_hook() -> Result<()> {
+    if let Err(e) = disable_detours() {
+        lifecycle::emit(HookEvent::Error(format!("{:#}", e)));
+        return Err(e);
+    }
 
-        let ui = imgui.frame();
-        ui.show_demo_window(&mut true);
+    discard_state();
+    logging::free_own_console();
+    lifecycle::emit(HookEvent::ShutdownComplete);
 
-        renderer.render(ui);
+    Ok(())
+}
 
-        ::std::thread::sleep(::std::time::Duration::new(0, 1_000_000_000u32 / 60));
-    }*/
+fn disable_detours() -> Result<()> {
+    if OpenGl32wglSwapBuffers.is_enabled() {
+        unsafe { OpenGl32wglSwapBuffers.disable() }?;
+    }
+    if Gdi32SwapBuffers.is_enabled() {
+        unsafe { Gdi32SwapBuffers.disable() }?;
+    }
+    if OpenGl32wglSwapLayerBuffers.is_enabled() {
+        unsafe { OpenGl32wglSwapLayerBuffers.disable() }?;
+    }
 
-    unsafe { OpenGl32wglSwapBuffers.call(dc) }
+    Ok(())
 }
 
-pub type FnOpenGl32wglSwapBuffers = unsafe extern "system" fn(HDC) -> ();
+/// Drops the overlay and unhooks the game window. Also recovers from a panic while rendering,
+/// which left the state half-way through a frame: the overlay comes up fresh with the next one.
+fn discard_state() {
+    let state = STATE.lock().unwrap_or_else(PoisonError::into_inner).take();
+    STATE.clear_poison();
+
+    if let Some(state) = state {
+        input::uninstall(state.input_mode, state.input_hwnd);
+        ready::mark_uninitialized();
+    }
+    // The next setup may well be on another context.
+    gl::unload();
+    cursor::reset();
+}
 
 fn main() -> Result<()> {
-    create_debug_console()?;
-    println!("Created debug console");
+    // End users of release builds don't get a console window popping up.
+    enable_debug_console(cfg!(debug_assertions)).context("Failed setting up debug output")?;
+    log_info!("Set up debug output");
 
-    let x = get_module_library("opengl32.dll", "wglSwapBuffers")?;
-    let y: FnOpenGl32wglSwapBuffers = unsafe { mem::transmute(x) };
-    unsafe { OpenGl32wglSwapBuffers.initialize(y, wglSwapBuffers_detour) }?;
-    println!("Initialized detour");
+    Ok(init_hook(PresentHook::default())?)
+}
 
-    unsafe { OpenGl32wglSwapBuffers.enable() }?;
-    println!("Enabled detour");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::update_config, test_util};
+    use std::sync::{atomic::AtomicUsize, Arc, Barrier};
+
+    /// Overlay state without a window or GL, as `run_headless_frame` sets it up.
+    fn headless_state() -> HookState {
+        update_config(|config| {
+            config.renderer = RendererKind::Null;
+            config.input_mode = InputMode::Polling;
+        });
+        create_state(HDC(0)).unwrap()
+    }
 
-    Ok(())
+    #[test]
+    fn names_with_nul_are_invalid() {
+        assert!(matches!(
+            get_module_library("opengl32.dll", "wglSwap\0Buffers"),
+            Err(HookError::InvalidName(name)) if name == "wglSwap\0Buffers"
+        ));
+        assert!(matches!(
+            get_module_library("opengl32\0.dll", "wglSwapBuffers"),
+            Err(HookError::InvalidName(name)) if name == "opengl32\0.dll"
+        ));
+    }
+
+    #[test]
+    fn missing_gl_functions_are_null() {
+        assert!(gl_get_proc_address("glNoSuchFunction").is_null());
+        assert!(gl_get_proc_address("glViewport\0").is_null());
+    }
+
+    #[test]
+    fn init_hook_returns_hook_error() {
+        let _lock = test_util::lock();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        set_lifecycle_callback(move |event| seen.lock().unwrap().push(event));
+        let targets = HookTargets {
+            wgl_swap_buffers: ("opengl32.dll".to_owned(), "wglSwap\0Buffers".to_owned()),
+            ..HookTargets::default()
+        };
+
+        let error = HookError::InvalidName("wglSwap\0Buffers".to_owned());
+        assert_eq!(
+            init_hook_with_targets(PresentHook::WglSwapBuffers, &targets),
+            Err(error.clone())
+        );
+        set_lifecycle_callback(|_| {});
+        assert_eq!(
+            *events.lock().unwrap(),
+            [HookEvent::Error(error.to_string())]
+        );
+    }
+
+    #[test]
+    fn detour_guard_refuses_nested_entry() {
+        let outer = DetourGuard::enter();
+        assert!(outer.is_some());
+        // A present from inside the detour (the game's own hook chain, a render callback).
+        assert!(DetourGuard::enter().is_none());
+        assert!(IN_DETOUR.get());
+        // Other threads have their own flag.
+        assert!(thread::spawn(|| DetourGuard::enter().is_some())
+            .join()
+            .unwrap());
+
+        drop(outer);
+        assert!(!IN_DETOUR.get());
+        assert!(DetourGuard::enter().is_some());
+        assert!(!IN_DETOUR.get());
+    }
+
+    #[test]
+    fn detour_guard_cleared_by_unwinding() {
+        let result = std::panic::catch_unwind(|| {
+            let _guard = DetourGuard::enter().unwrap();
+            panic!("in the detour");
+        });
+
+        assert!(result.is_err());
+        assert!(!IN_DETOUR.get());
+    }
+
+    #[test]
+    fn concurrent_presents_initialize_once() {
+        let _lock = test_util::lock();
+        update_config(|config| config.renderer = RendererKind::Null);
+        discard_state();
+        let initialized = Arc::new(AtomicUsize::new(0));
+        let counter = initialized.clone();
+        set_lifecycle_callback(move |event| {
+            if let HookEvent::OverlayInitialized { .. } = event {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        // Contexts on several threads presenting at the same time.
+        let start = Arc::new(Barrier::new(8));
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let start = start.clone();
+                thread::spawn(move || {
+                    start.wait();
+                    run_headless_frame()
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap().unwrap();
+        }
+
+        assert_eq!(initialized.load(Ordering::Relaxed), 1);
+        assert!(STATE.lock().unwrap().is_some());
+
+        // Shut down, the next present sets the overlay up again.
+        discard_state();
+        run_headless_frame().unwrap();
+        assert_eq!(initialized.load(Ordering::Relaxed), 2);
+
+        set_lifecycle_callback(|_| {});
+        discard_state();
+    }
+
+    #[test]
+    fn follow_window_picks_up_new_window() {
+        let _lock = test_util::lock();
+        let mut state = headless_state();
+        state.hwnd = HWND(0x100);
+        state.input_hwnd = HWND(0x100);
+
+        follow_window(&mut state, HWND(0x200));
+        assert_eq!(state.hwnd, HWND(0x200));
+        // Input follows unless it was pointed elsewhere.
+        assert_eq!(state.input_hwnd, HWND(0x200));
+
+        // Presents without a window keep the last one.
+        follow_window(&mut state, HWND(0));
+        assert_eq!(state.hwnd, HWND(0x200));
+        assert_eq!(state.input_hwnd, HWND(0x200));
+    }
 }
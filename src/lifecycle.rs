@@ -0,0 +1,47 @@
+//! Notifications about the hook coming up and going down, for hosts showing its status, see
+//! [`set_lifecycle_callback`].
+
+use std::sync::{Arc, Mutex};
+use windows::Win32::Foundation::HWND;
+
+type LifecycleFn = Arc<dyn Fn(HookEvent) + Send + Sync>;
+
+static CALLBACK: Mutex<Option<LifecycleFn>> = Mutex::new(None);
+
+/// Something that happened to the hook, see [`set_lifecycle_callback`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookEvent {
+    /// [`init_hook`](crate::init_hook) detoured the present functions.
+    DetourEnabled,
+    /// The overlay came up on the first frame presented to `hwnd`.
+    OverlayInitialized { hwnd: HWND },
+    /// The game replaced its window procedure and the window was subclassed again.
+    ReHooked,
+    /// [`shutdown_hook`](crate::shutdown_hook) unhooked everything and dropped the overlay.
+    ShutdownComplete,
+    /// [`init_hook`](crate::init_hook) or [`shutdown_hook`](crate::shutdown_hook) failed, with
+    /// the whole chain of causes. The call returns the same error.
+    ///
+    /// Also sent when a render or background draw callback panicked and was removed.
+    Error(String),
+}
+
+/// Sets the callback told about the hook's lifecycle, replacing the previous one.
+///
+/// It is called on the thread the event happens on: the caller's for `init_hook` and
+/// `shutdown_hook`, the render thread in the middle of a present for the others. Calling
+/// `shutdown_hook` from it there would wait for the present it is called from, hand such work off
+/// to another thread.
+pub fn set_lifecycle_callback(f: impl Fn(HookEvent) + Send + Sync + 'static) {
+    *CALLBACK.lock().unwrap() = Some(Arc::new(f));
+}
+
+/// Tells the lifecycle callback about `event`, if there is one.
+pub(crate) fn emit(event: HookEvent) {
+    // Outside the lock, the callback may well call `set_lifecycle_callback` itself.
+    let callback = CALLBACK.lock().unwrap().clone();
+
+    if let Some(callback) = callback {
+        callback(event);
+    }
+}
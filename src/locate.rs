@@ -0,0 +1,127 @@
+//! Finding a function in a module's code by its bytes or its offset, for when `GetProcAddress`
+//! can't be trusted with it, see `HookConfig::wgl_swap_buffers_location`.
+
+use anyhow::{anyhow, Result};
+use std::{mem, slice};
+use windows::Win32::Foundation::HINSTANCE;
+
+use crate::error::HookError;
+
+// PE layout, see the `IMAGE_DOS_HEADER`, `IMAGE_NT_HEADERS` and `IMAGE_SECTION_HEADER` docs.
+const E_LFANEW_OFFSET: usize = 0x3c;
+const NUMBER_OF_SECTIONS_OFFSET: usize = 4 + 2;
+const SIZE_OF_OPTIONAL_HEADER_OFFSET: usize = 4 + 16;
+const SECTION_TABLE_OFFSET: usize = 4 + 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const VIRTUAL_SIZE_OFFSET: usize = 8;
+const VIRTUAL_ADDRESS_OFFSET: usize = 12;
+const CHARACTERISTICS_OFFSET: usize = 36;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// Where a function is in its module, instead of where its export says.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FunctionLocation {
+    /// The function's first bytes, `None` for bytes that vary (relocated addresses). Has to match
+    /// exactly one place in the module's code, see [`FunctionLocation::pattern`].
+    Pattern(Vec<Option<u8>>),
+    /// Offset of the function from the module's base address. Only valid for the exact build of
+    /// the module it was taken from.
+    Offset(usize),
+}
+
+impl FunctionLocation {
+    /// Parses an IDA-style pattern: hex bytes separated by spaces, `?` or `??` for any byte, e.g.
+    /// `"48 89 5C 24 ?? 57"`. Fails with [`HookError::InvalidPattern`] otherwise.
+    pub fn pattern(pattern: &str) -> Result<Self> {
+        let invalid = || HookError::InvalidPattern(pattern.to_owned());
+
+        let bytes = pattern
+            .split_whitespace()
+            .map(|byte| match byte {
+                "?" | "??" => Ok(None),
+                _ if byte.len() == 2 => u8::from_str_radix(byte, 16)
+                    .map(Some)
+                    .map_err(|_| invalid()),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if bytes.iter().all(Option::is_none) {
+            return Err(invalid().into());
+        }
+
+        Ok(FunctionLocation::Pattern(bytes))
+    }
+}
+
+/// The executable sections of the module loaded at `module`.
+///
+/// # Safety
+///
+/// `module` must be a loaded module, its headers are read in place.
+unsafe fn code_sections(module: HINSTANCE) -> Vec<&'static [u8]> {
+    let base = module.0 as *const u8;
+    let read_u16 = |offset: usize| (base.add(offset) as *const u16).read_unaligned() as usize;
+    let read_u32 = |offset: usize| (base.add(offset) as *const u32).read_unaligned();
+
+    let nt_headers = read_u32(E_LFANEW_OFFSET) as usize;
+    let sections = read_u16(nt_headers + NUMBER_OF_SECTIONS_OFFSET);
+    let section_table =
+        nt_headers + SECTION_TABLE_OFFSET + read_u16(nt_headers + SIZE_OF_OPTIONAL_HEADER_OFFSET);
+
+    (0..sections)
+        .map(|i| section_table + i * SECTION_HEADER_SIZE)
+        .filter(|&header| read_u32(header + CHARACTERISTICS_OFFSET) & IMAGE_SCN_MEM_EXECUTE != 0)
+        .map(|header| {
+            let size = read_u32(header + VIRTUAL_SIZE_OFFSET);
+            let address = read_u32(header + VIRTUAL_ADDRESS_OFFSET);
+            slice::from_raw_parts(base.add(address as usize), size as usize)
+        })
+        .collect()
+}
+
+/// Address of the function `location` describes in `module`, which must be loaded.
+pub(crate) fn find(
+    module: HINSTANCE,
+    location: &FunctionLocation,
+) -> Result<unsafe extern "system" fn() -> isize> {
+    let sections = unsafe { code_sections(module) };
+
+    let address = match location {
+        FunctionLocation::Offset(offset) => {
+            let address = module.0 as usize + offset;
+            // Jumping into data or the middle of nowhere would take the game down on first call.
+            if !sections
+                .iter()
+                .any(|section| section.as_ptr_range().contains(&(address as *const u8)))
+            {
+                return Err(anyhow!("Offset {:#x} isn't in the module's code", offset));
+            }
+            address
+        }
+        FunctionLocation::Pattern(pattern) if pattern.is_empty() => {
+            return Err(anyhow!("Empty pattern"));
+        }
+        FunctionLocation::Pattern(pattern) => {
+            let mut matches = sections.iter().flat_map(|section| {
+                section
+                    .windows(pattern.len())
+                    .filter(|window| {
+                        window.iter().zip(pattern).all(|(byte, expected)| {
+                            expected.is_none_or(|expected| *byte == expected)
+                        })
+                    })
+                    .map(|window| window.as_ptr() as usize)
+            });
+            let address = matches
+                .next()
+                .ok_or_else(|| anyhow!("Pattern not found in the module's code"))?;
+            // Detouring the wrong one of two look-alikes would be worse than not hooking at all.
+            if matches.next().is_some() {
+                return Err(anyhow!("Pattern found more than once, make it longer"));
+            }
+            address
+        }
+    };
+
+    Ok(unsafe { mem::transmute::<usize, unsafe extern "system" fn() -> isize>(address) })
+}
@@ -0,0 +1,180 @@
+//! Where the hook's output goes, see [`enable_debug_console`], and the debug output for the
+//! per-frame and per-message paths, off unless [`set_logging`] enables it.
+
+use anyhow::Result;
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    os::windows::io::AsRawHandle,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+use windows::Win32::{
+    Foundation::{GetLastError, ERROR_ACCESS_DENIED, HANDLE, WIN32_ERROR},
+    System::Console::{
+        AllocConsole, FreeConsole, GetConsoleWindow, SetStdHandle, STD_OUTPUT_HANDLE,
+    },
+    UI::WindowsAndMessaging::{ShowWindow, SW_HIDE, SW_SHOW},
+};
+
+use crate::error::HookError;
+
+const LOG_FILE_NAME: &str = "opengl-imgui-hook.log";
+
+static LOG_ENABLED: AtomicBool = AtomicBool::new(false);
+// What stdout was redirected to, kept open for as long as it is stdout.
+static STDOUT: Mutex<Option<File>> = Mutex::new(None);
+// Whether the console is one `enable_debug_console` allocated, rather than the host's.
+static OWNS_CONSOLE: AtomicBool = AtomicBool::new(false);
+
+/// Prints like `println!`, but only while logging is enabled. The arguments aren't formatted
+/// otherwise, so it costs a single atomic load on the hot path.
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Prints a status message (detours enabled, window changed, ...), always.
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        println!($($arg)*)
+    };
+}
+
+/// Prints a failure the hook recovered from or reports to the caller, always.
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        println!("Error: {}", format_args!($($arg)*))
+    };
+}
+
+/// Turns the hook's debug output (every frame and window message) on or off. Off by default,
+/// status messages and errors are printed regardless.
+pub fn set_logging(enabled: bool) {
+    LOG_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled() -> bool {
+    LOG_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sends the hook's output to a console window when `enabled` (allocating one unless the process
+/// already has one), otherwise to `opengl-imgui-hook.log` in the temp directory. Can be called
+/// again to switch.
+///
+/// The DLL build opens the console in debug builds and writes the file in release builds. Fails
+/// with [`HookError::ConsoleAllocFailed`] if there is no console and none can be allocated, and
+/// with [`HookError::ConsoleFailed`] if the output can't be redirected.
+pub fn enable_debug_console(enabled: bool) -> Result<()> {
+    let output = if enabled {
+        if unsafe { AllocConsole() }.as_bool() {
+            // A new console comes with its own standard handles.
+            *STDOUT.lock().unwrap() = None;
+            OWNS_CONSOLE.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        check_existing_console(unsafe { GetLastError() })?;
+        // Stdout still goes to the existing console unless we sent it to the log file before.
+        if STDOUT.lock().unwrap().is_none() {
+            return Ok(());
+        }
+        OpenOptions::new().write(true).open("CONOUT$")?
+    } else {
+        File::create(env::temp_dir().join(LOG_FILE_NAME))?
+    };
+
+    // `println!` looks the handle up on every write, so this redirects all output from now on.
+    let handle = HANDLE(output.as_raw_handle() as isize);
+    if !unsafe { SetStdHandle(STD_OUTPUT_HANDLE, handle) }.as_bool() {
+        return Err(HookError::ConsoleFailed {
+            function: "SetStdHandle".to_owned(),
+            last_error: unsafe { GetLastError() }.0,
+        }
+        .into());
+    }
+    *STDOUT.lock().unwrap() = Some(output);
+
+    Ok(())
+}
+
+/// Checks that a failed `AllocConsole` (failing with `error`) still leaves a console to write to:
+/// `ERROR_ACCESS_DENIED` means the process already has one (e.g. a console-hosted game).
+fn check_existing_console(error: WIN32_ERROR) -> Result<(), HookError> {
+    if error == ERROR_ACCESS_DENIED {
+        Ok(())
+    } else {
+        Err(HookError::ConsoleAllocFailed {
+            last_error: error.0,
+        })
+    }
+}
+
+/// Shows or hides the process's console window, e.g. the one [`enable_debug_console`] opened.
+/// Output keeps going there while it is hidden. Does nothing without a console.
+pub fn set_console_visible(visible: bool) {
+    let window = unsafe { GetConsoleWindow() };
+    if window.0 == 0 {
+        return;
+    }
+
+    // Returns the previous visibility, not success.
+    unsafe { ShowWindow(window, if visible { SW_SHOW } else { SW_HIDE }) };
+}
+
+/// Detaches the process from its console, closing the window unless another process shares it.
+/// The hook's output goes to the log file afterwards, see [`enable_debug_console`].
+///
+/// This also takes a console the host process created away from it, [`shutdown_hook`] only frees
+/// the one [`enable_debug_console`] allocated. Fails with [`HookError::ConsoleFailed`] if there is
+/// no console to free.
+///
+/// [`shutdown_hook`]: crate::shutdown_hook
+pub fn free_console() -> Result<()> {
+    if !unsafe { FreeConsole() }.as_bool() {
+        return Err(HookError::ConsoleFailed {
+            function: "FreeConsole".to_owned(),
+            last_error: unsafe { GetLastError() }.0,
+        }
+        .into());
+    }
+    OWNS_CONSOLE.store(false, Ordering::Relaxed);
+
+    // Stdout still refers to the console.
+    enable_debug_console(false)
+}
+
+/// Frees the console if `enable_debug_console` allocated it, for `shutdown_hook`.
+pub(crate) fn free_own_console() {
+    if OWNS_CONSOLE.load(Ordering::Relaxed) {
+        if let Err(e) = free_console() {
+            log_error!("Failed freeing console: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::Foundation::ERROR_NOT_ENOUGH_MEMORY;
+
+    #[test]
+    fn existing_console_is_kept() {
+        assert!(check_existing_console(ERROR_ACCESS_DENIED).is_ok());
+    }
+
+    #[test]
+    fn other_alloc_failures_are_reported() {
+        let result = check_existing_console(ERROR_NOT_ENOUGH_MEMORY);
+
+        assert!(matches!(
+            result,
+            Err(HookError::ConsoleAllocFailed { last_error: 8 })
+        ));
+    }
+}
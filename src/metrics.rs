@@ -0,0 +1,151 @@
+//! Cheap counters about what the hook did, for tuning and bug reports, and the optional
+//! on-screen readout of the overlay's own cost, see [`set_show_metrics`].
+
+use imgui::{DrawCmd, DrawData, Io, Ui};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+static FRAMES: AtomicU64 = AtomicU64::new(0);
+static DROPPED_FRAMES: AtomicU64 = AtomicU64::new(0);
+static RENDER_INTERVAL_NANOS: AtomicU64 = AtomicU64::new(0);
+
+static SHOW_METRICS: AtomicBool = AtomicBool::new(false);
+static LAST_FRAME: Mutex<Option<FrameStats>> = Mutex::new(None);
+
+const METRICS_POS: [f32; 2] = [8.0, 8.0];
+const METRICS_PADDING: f32 = 4.0;
+const METRICS_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const METRICS_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// What the overlay's last built frame held, see [`last_frame_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Seconds since the frame built before it.
+    pub delta_time: f32,
+    pub vertices: usize,
+    pub indices: usize,
+    pub draw_calls: usize,
+    /// In ImGui's units, see `Io::display_size`.
+    pub display_size: [f32; 2],
+}
+
+/// Stats of the last UI rebuild, `None` before the first one. Geometry counts are zero for frames
+/// built while the overlay is hidden.
+///
+/// For watching the overlay from the host: a stalled overlay stops updating
+/// [`frames`], one drawing nothing reports no vertices.
+pub fn last_frame_stats() -> Option<FrameStats> {
+    *LAST_FRAME.lock().unwrap()
+}
+
+/// Frames the game presented since start (or the last [`reset_metrics`]).
+pub fn frames() -> u64 {
+    FRAMES.load(Ordering::Relaxed)
+}
+
+/// Frames in which the overlay skipped building the UI, out of [`frames`]: it wasn't drawn (not
+/// initialized, no window, minimized), or last frame's was drawn again under
+/// `HookConfig::min_render_interval_us`.
+pub fn dropped_frames() -> u64 {
+    DROPPED_FRAMES.load(Ordering::Relaxed)
+}
+
+/// Time between the last two UI rebuilds, zero until there were two. With
+/// `HookConfig::min_render_interval_us` set this is the interval actually achieved.
+pub fn render_interval() -> Duration {
+    Duration::from_nanos(RENDER_INTERVAL_NANOS.load(Ordering::Relaxed))
+}
+
+pub fn reset_metrics() {
+    FRAMES.store(0, Ordering::Relaxed);
+    DROPPED_FRAMES.store(0, Ordering::Relaxed);
+    RENDER_INTERVAL_NANOS.store(0, Ordering::Relaxed);
+    *LAST_FRAME.lock().unwrap() = None;
+}
+
+pub(crate) fn record_frame() {
+    FRAMES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// To be called from every path that skips building the UI for a frame.
+pub(crate) fn record_dropped_frame() {
+    DROPPED_FRAMES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_render_interval(interval: Duration) {
+    RENDER_INTERVAL_NANOS.store(interval.as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// Shows the overlay's frame rate, frame time and draw calls in the top left corner, on top of
+/// everything else including the render callback's windows. Off by default.
+///
+/// The numbers are the overlay's: with `HookConfig::min_render_interval_us` set they count UI
+/// rebuilds, not the game's frames.
+pub fn set_show_metrics(show: bool) {
+    SHOW_METRICS.store(show, Ordering::Relaxed);
+}
+
+/// Records the frame that was just built, `rendered` unless the overlay is hidden.
+pub(crate) fn record_frame_stats(io: &Io, rendered: bool) {
+    let mut stats = FrameStats {
+        delta_time: io.delta_time,
+        display_size: io.display_size,
+        ..FrameStats::default()
+    };
+
+    // The renderer consumes the `Ui`, but the draw data stays valid until the next frame starts.
+    let draw_data = unsafe { (imgui::sys::igGetDrawData() as *const DrawData).as_ref() };
+    if let Some(draw_data) = draw_data.filter(|_| rendered) {
+        stats.vertices = draw_data.total_vtx_count as usize;
+        stats.indices = draw_data.total_idx_count as usize;
+        stats.draw_calls = draw_data
+            .draw_lists()
+            .flat_map(|draw_list| draw_list.commands())
+            .filter(|cmd| matches!(cmd, DrawCmd::Elements { .. }))
+            .count();
+    }
+
+    *LAST_FRAME.lock().unwrap() = Some(stats);
+}
+
+/// Draws the readout into the current frame, if it is turned on.
+pub(crate) fn draw(ui: &Ui) {
+    if !SHOW_METRICS.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let io = ui.io();
+    let text = format!(
+        "{:.0} FPS\n{:.2} ms\n{} draw calls",
+        io.framerate,
+        io.delta_time * 1000.0,
+        last_frame_stats().map_or(0, |stats| stats.draw_calls)
+    );
+    let size = ui.calc_text_size(&text);
+
+    let draw_list = ui.get_foreground_draw_list();
+    draw_list
+        .add_rect(
+            METRICS_POS,
+            [
+                METRICS_POS[0] + size[0] + 2.0 * METRICS_PADDING,
+                METRICS_POS[1] + size[1] + 2.0 * METRICS_PADDING,
+            ],
+            METRICS_BACKGROUND,
+        )
+        .filled(true)
+        .build();
+    draw_list.add_text(
+        [
+            METRICS_POS[0] + METRICS_PADDING,
+            METRICS_POS[1] + METRICS_PADDING,
+        ],
+        METRICS_TEXT,
+        &text,
+    );
+}
@@ -0,0 +1,301 @@
+//! Experimental: drawing every overlay frame a second time, into a window owned by the crate.
+//!
+//! Enabled through `HookConfig::mirror_window`, e.g. to watch the overlay on another monitor or
+//! to capture it without the game underneath. The window only displays, it takes no input, and
+//! closing it turns the option off again.
+//!
+//! This is not free. Per frame the render thread:
+//! - switches to the mirror's GL context and back, which makes the driver flush the game's
+//!   command stream; on some drivers this alone costs milliseconds,
+//! - uploads all vertex and index data a second time,
+//! - swaps the mirror's buffers, which can block on its own vsync.
+//!
+//! Limitations:
+//! - The mirror context shares objects with the game's context (`wglShareLists`) so ImGui's
+//!   texture ids stay valid. That fails if the two pixel formats are incompatible, and the
+//!   sharing is lost if the game recreates its context.
+//! - Drawing uses the crate's own GL 3.3 core renderer with the entry points loaded for the game's
+//!   context, which WGL only guarantees to work for contexts on the same driver.
+//! - Draw commands using raw callbacks are skipped.
+//! - The draw data is stretched to the mirror's client area.
+
+use anyhow::{anyhow, Result};
+use imgui::DrawData;
+use std::{iter, mem, ptr, sync::Mutex};
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{GetLastError, HWND, LPARAM, LRESULT, RECT, WPARAM},
+        Graphics::{
+            Gdi::{GetDC, ReleaseDC, HDC},
+            OpenGL::{
+                wglCreateContext, wglDeleteContext, wglGetCurrentContext, wglGetCurrentDC,
+                wglMakeCurrent, wglShareLists, ChoosePixelFormat, SetPixelFormat, SwapBuffers,
+                HGLRC, PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_MAIN_PLANE, PFD_SUPPORT_OPENGL,
+                PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
+            },
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect,
+            PeekMessageW, RegisterClassW, TranslateMessage, UnregisterClassW, CS_OWNDC,
+            CW_USEDEFAULT, HMENU, MSG, PM_REMOVE, WINDOW_EX_STYLE, WM_CLOSE, WNDCLASSW,
+            WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+        },
+    },
+};
+
+use crate::{
+    config,
+    gl::{self, GLbitfield},
+    gl_renderer::GlRenderer,
+};
+
+const GL_COLOR_BUFFER_BIT: GLbitfield = 0x0000_4000;
+
+const CLASS_NAME: &str = "opengl-imgui-hook mirror";
+const WINDOW_TITLE: &str = "ImGui overlay (mirror)";
+
+// Only touched on the render thread, like the ImGui context itself.
+static MIRROR: Mutex<Option<Mirror>> = Mutex::new(None);
+
+struct Mirror {
+    class_name: Vec<u16>,
+    hwnd: HWND,
+    dc: HDC,
+    context: HGLRC,
+    renderer: Option<GlRenderer>,
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(iter::once(0)).collect()
+}
+
+fn last_error(function: &str) -> anyhow::Error {
+    anyhow!(
+        "Failed {}, GetLastError: {}",
+        function,
+        unsafe { GetLastError() }.0
+    )
+}
+
+/// Makes `context` current and switches back to the previously current context when dropped.
+struct ContextSwitch {
+    prev_dc: HDC,
+    prev_context: HGLRC,
+}
+
+impl ContextSwitch {
+    fn to(dc: HDC, context: HGLRC) -> Result<Self> {
+        let switch = ContextSwitch {
+            prev_dc: unsafe { wglGetCurrentDC() },
+            prev_context: unsafe { wglGetCurrentContext() },
+        };
+
+        if !unsafe { wglMakeCurrent(dc, context) }.as_bool() {
+            return Err(last_error("wglMakeCurrent"));
+        }
+
+        Ok(switch)
+    }
+}
+
+impl Drop for ContextSwitch {
+    fn drop(&mut self) {
+        unsafe { wglMakeCurrent(self.prev_dc, self.prev_context) };
+    }
+}
+
+unsafe extern "system" fn mirror_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_CLOSE {
+        // Torn down on the next frame, from the render thread.
+        config::update_config(|config| config.mirror_window = false);
+        return LRESULT(0);
+    }
+
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+impl Mirror {
+    /// Creates the window and a context sharing objects with the current (the game's) one.
+    fn create(size: [f32; 2]) -> Result<Self> {
+        let game_context = unsafe { wglGetCurrentContext() };
+        if game_context.0 == 0 {
+            return Err(anyhow!("No GL context is current"));
+        }
+
+        let instance = unsafe { GetModuleHandleW(PCWSTR::null()) }?;
+        let class_name = wide(CLASS_NAME);
+        let window_title = wide(WINDOW_TITLE);
+
+        let class = WNDCLASSW {
+            style: CS_OWNDC,
+            lpfnWndProc: Some(mirror_wnd_proc),
+            hInstance: instance,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+        if unsafe { RegisterClassW(&class) } == 0 {
+            return Err(last_error("RegisterClassW"));
+        }
+
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR(window_title.as_ptr()),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                size[0] as i32,
+                size[1] as i32,
+                HWND(0),
+                HMENU(0),
+                instance,
+                ptr::null(),
+            )
+        };
+
+        // From here on `Drop` cleans up whatever has been created.
+        let mut mirror = Mirror {
+            class_name,
+            hwnd,
+            dc: HDC(0),
+            context: HGLRC(0),
+            renderer: None,
+        };
+
+        if hwnd.0 == 0 {
+            return Err(last_error("CreateWindowExW"));
+        }
+
+        mirror.dc = unsafe { GetDC(hwnd) };
+
+        let pfd = PIXELFORMATDESCRIPTOR {
+            nSize: mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
+            nVersion: 1,
+            dwFlags: PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER,
+            iPixelType: PFD_TYPE_RGBA,
+            cColorBits: 32,
+            cAlphaBits: 8,
+            iLayerType: PFD_MAIN_PLANE,
+            ..Default::default()
+        };
+        let format = unsafe { ChoosePixelFormat(mirror.dc, &pfd) };
+        if format == 0 {
+            return Err(last_error("ChoosePixelFormat"));
+        }
+        if !unsafe { SetPixelFormat(mirror.dc, format, &pfd) }.as_bool() {
+            return Err(last_error("SetPixelFormat"));
+        }
+
+        mirror.context = unsafe { wglCreateContext(mirror.dc) }?;
+
+        // Must happen before the new context owns any objects.
+        if !unsafe { wglShareLists(game_context, mirror.context) }.as_bool() {
+            return Err(last_error("wglShareLists"));
+        }
+
+        let _switch = ContextSwitch::to(mirror.dc, mirror.context)?;
+        mirror.renderer = Some(GlRenderer::new()?);
+
+        Ok(mirror)
+    }
+
+    fn pump_messages(&self) {
+        let mut msg = MSG::default();
+        while unsafe { PeekMessageW(&mut msg, self.hwnd, 0, 0, PM_REMOVE) }.as_bool() {
+            unsafe {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+
+    fn render(&self, draw_data: &DrawData) -> Result<()> {
+        let (Some(renderer), Some(gl)) = (&self.renderer, gl::get()) else {
+            return Ok(());
+        };
+
+        let mut client = RECT::default();
+        unsafe { GetClientRect(self.hwnd, &mut client) };
+
+        let _switch = ContextSwitch::to(self.dc, self.context)?;
+        unsafe {
+            (gl.clear)(GL_COLOR_BUFFER_BIT);
+        }
+        renderer.render(
+            draw_data,
+            [client.right as f32, client.bottom as f32],
+            [0, 0],
+            None,
+        );
+        unsafe { SwapBuffers(self.dc) };
+
+        Ok(())
+    }
+}
+
+impl Drop for Mirror {
+    fn drop(&mut self) {
+        unsafe {
+            if self.context.0 != 0 {
+                // The renderer's objects live in the mirror context.
+                if let Ok(_switch) = ContextSwitch::to(self.dc, self.context) {
+                    self.renderer = None;
+                }
+                wglDeleteContext(self.context);
+            }
+            if self.dc.0 != 0 {
+                ReleaseDC(self.hwnd, self.dc);
+            }
+            if self.hwnd.0 != 0 {
+                DestroyWindow(self.hwnd);
+            }
+            UnregisterClassW(
+                PCWSTR(self.class_name.as_ptr()),
+                GetModuleHandleW(PCWSTR::null()).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+/// Renders the frame ImGui just produced into the mirror window, creating or destroying the
+/// window as `enabled` changes. Call with the game's context current, after the overlay rendered.
+pub(crate) fn update(enabled: bool) {
+    let mut mirror = MIRROR.lock().unwrap();
+
+    if !enabled {
+        *mirror = None;
+        return;
+    }
+
+    // The renderer consumes the `Ui`, but the draw data stays valid until the next frame starts.
+    let Some(draw_data) = (unsafe { (imgui::sys::igGetDrawData() as *const DrawData).as_ref() })
+    else {
+        return;
+    };
+
+    if mirror.is_none() {
+        match Mirror::create(draw_data.display_size) {
+            Ok(created) => *mirror = Some(created),
+            Err(e) => {
+                log_error!("Failed creating mirror window: {}", e);
+                // Don't try again every frame.
+                config::update_config(|config| config.mirror_window = false);
+                return;
+            }
+        }
+    }
+
+    let mirror = mirror.as_ref().unwrap();
+    mirror.pump_messages();
+    if let Err(e) = mirror.render(draw_data) {
+        log_error!("Failed rendering mirror window: {}", e);
+    }
+}
@@ -0,0 +1,663 @@
+//! An in-memory stand-in for the GL driver. Test infrastructure only, never enable the `mock-gl`
+//! feature in a build that goes into a game.
+//!
+//! With the feature on, every GL function the crate resolves (its own tables as well as the ones
+//! handed to `imgui_opengl_renderer`) comes from here instead of the driver. Nothing is drawn:
+//! calls are recorded, and the state they touch (caps, bindings, blend setup, viewport, scissor,
+//! pixel store) is tracked so `glGet*`/`glIsEnabled` answer like a driver would. That makes it
+//! possible to check the crate leaves the GL state exactly as the game left it:
+//!
+//! ```ignore
+//! mock_gl::install();
+//! mock_gl::set_enabled(GL_DEPTH_TEST, true);
+//! let before = mock_gl::snapshot();
+//! run_headless_frame()?;
+//! assert_eq!(mock_gl::snapshot(), before);
+//! ```
+//!
+//! The state is process-wide, tests using it must not run in parallel.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::{c_char, c_void},
+    sync::Mutex,
+};
+
+use crate::gl::{
+    self, GLbitfield, GLboolean, GLenum, GLint, GLsizei, GLubyte, GLuint, GL_DEPTH_WRITEMASK,
+    GL_DRAW_FRAMEBUFFER, GL_DRAW_FRAMEBUFFER_BINDING, GL_READ_FRAMEBUFFER,
+    GL_READ_FRAMEBUFFER_BINDING, GL_SCISSOR_BOX, GL_TRUE, GL_VERSION,
+};
+
+type GLfloat = f32;
+type GLsizeiptr = isize;
+
+const GL_UNPACK_ROW_LENGTH: GLenum = 0x0CF2;
+const GL_UNPACK_ALIGNMENT: GLenum = 0x0CF5;
+const GL_PACK_ROW_LENGTH: GLenum = 0x0D02;
+const GL_PACK_SKIP_ROWS: GLenum = 0x0D03;
+const GL_PACK_SKIP_PIXELS: GLenum = 0x0D04;
+const GL_PACK_ALIGNMENT: GLenum = 0x0D05;
+const GL_TEXTURE_2D: GLenum = 0x0DE1;
+const GL_VIEWPORT: GLenum = 0x0BA2;
+const GL_BLEND_EQUATION_RGB: GLenum = 0x8009;
+const GL_TEXTURE_BINDING_2D: GLenum = 0x8069;
+const GL_BLEND_DST_RGB: GLenum = 0x80C8;
+const GL_BLEND_SRC_RGB: GLenum = 0x80C9;
+const GL_BLEND_DST_ALPHA: GLenum = 0x80CA;
+const GL_BLEND_SRC_ALPHA: GLenum = 0x80CB;
+const GL_ACTIVE_TEXTURE: GLenum = 0x84E0;
+const GL_VERTEX_ARRAY_BINDING: GLenum = 0x85B5;
+const GL_BLEND_EQUATION_ALPHA: GLenum = 0x883D;
+const GL_ARRAY_BUFFER: GLenum = 0x8892;
+const GL_ELEMENT_ARRAY_BUFFER: GLenum = 0x8893;
+const GL_ARRAY_BUFFER_BINDING: GLenum = 0x8894;
+const GL_ELEMENT_ARRAY_BUFFER_BINDING: GLenum = 0x8895;
+const GL_CURRENT_PROGRAM: GLenum = 0x8B8D;
+const GL_FRAMEBUFFER: GLenum = 0x8D40;
+const GL_COMPILE_STATUS: GLenum = 0x8B81;
+const GL_LINK_STATUS: GLenum = 0x8B82;
+
+const GL_ONE: GLint = 1;
+const GL_FUNC_ADD: GLint = 0x8006;
+const GL_TEXTURE0: GLint = 0x84C0;
+
+/// One GL call, with its scalar arguments (pointers are left out).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlCall {
+    pub name: &'static str,
+    pub args: Vec<i64>,
+}
+
+/// The tracked GL state at one point in time, compare two to find what changed in between.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlSnapshot {
+    /// Caps enabled through `glEnable`.
+    pub enabled: BTreeSet<GLenum>,
+    /// Every value `glGetIntegerv` knows, by name. Single values take the first slot.
+    pub integers: BTreeMap<GLenum, [GLint; 4]>,
+}
+
+struct MockState {
+    calls: Vec<GlCall>,
+    state: GlSnapshot,
+    next_name: GLuint,
+}
+
+static MOCK: Mutex<MockState> = Mutex::new(MockState {
+    calls: Vec::new(),
+    state: GlSnapshot {
+        enabled: BTreeSet::new(),
+        integers: BTreeMap::new(),
+    },
+    next_name: 1,
+});
+
+/// Resolves the crate's GL function table from the mock. Call before the first frame.
+pub fn install() {
+    reset();
+    gl::load();
+}
+
+/// Forgets all calls and puts the state back to GL's defaults.
+pub fn reset() {
+    let mut mock = MOCK.lock().unwrap();
+    mock.calls.clear();
+    mock.state = GlSnapshot::default();
+    mock.next_name = 1;
+
+    // Every value the mock tracks has an entry, so snapshots taken before a value is first set
+    // compare equal to ones taken after it was set back to its default.
+    let integers = &mut mock.state.integers;
+    for name in [
+        GL_VIEWPORT,
+        GL_SCISSOR_BOX,
+        GL_TEXTURE_BINDING_2D,
+        GL_BLEND_DST_RGB,
+        GL_BLEND_DST_ALPHA,
+        GL_VERTEX_ARRAY_BINDING,
+        GL_ARRAY_BUFFER_BINDING,
+        GL_ELEMENT_ARRAY_BUFFER_BINDING,
+        GL_CURRENT_PROGRAM,
+        GL_DRAW_FRAMEBUFFER_BINDING,
+        GL_READ_FRAMEBUFFER_BINDING,
+        GL_UNPACK_ROW_LENGTH,
+        GL_PACK_ROW_LENGTH,
+        GL_PACK_SKIP_ROWS,
+        GL_PACK_SKIP_PIXELS,
+    ] {
+        integers.insert(name, [0; 4]);
+    }
+    integers.insert(GL_DEPTH_WRITEMASK, [GL_TRUE as GLint, 0, 0, 0]);
+    integers.insert(GL_ACTIVE_TEXTURE, [GL_TEXTURE0, 0, 0, 0]);
+    integers.insert(GL_BLEND_SRC_RGB, [GL_ONE, 0, 0, 0]);
+    integers.insert(GL_BLEND_SRC_ALPHA, [GL_ONE, 0, 0, 0]);
+    integers.insert(GL_BLEND_EQUATION_RGB, [GL_FUNC_ADD, 0, 0, 0]);
+    integers.insert(GL_BLEND_EQUATION_ALPHA, [GL_FUNC_ADD, 0, 0, 0]);
+    integers.insert(GL_UNPACK_ALIGNMENT, [4, 0, 0, 0]);
+    integers.insert(GL_PACK_ALIGNMENT, [4, 0, 0, 0]);
+}
+
+/// The calls made since the last `reset`/`clear_calls`, oldest first.
+pub fn calls() -> Vec<GlCall> {
+    MOCK.lock().unwrap().calls.clone()
+}
+
+pub fn clear_calls() {
+    MOCK.lock().unwrap().calls.clear();
+}
+
+pub fn snapshot() -> GlSnapshot {
+    MOCK.lock().unwrap().state.clone()
+}
+
+/// Sets a cap without recording a call, to set the scene up like a game would.
+pub fn set_enabled(cap: GLenum, enabled: bool) {
+    let mut mock = MOCK.lock().unwrap();
+    if enabled {
+        mock.state.enabled.insert(cap);
+    } else {
+        mock.state.enabled.remove(&cap);
+    }
+}
+
+/// Sets a `glGetIntegerv` value without recording a call.
+pub fn set_integer(name: GLenum, value: [GLint; 4]) {
+    MOCK.lock().unwrap().state.integers.insert(name, value);
+}
+
+fn record(name: &'static str, args: &[i64]) {
+    MOCK.lock().unwrap().calls.push(GlCall {
+        name,
+        args: args.to_vec(),
+    });
+}
+
+fn set(name: GLenum, value: [GLint; 4]) {
+    MOCK.lock().unwrap().state.integers.insert(name, value);
+}
+
+fn set_one(name: GLenum, value: GLint) {
+    set(name, [value, 0, 0, 0]);
+}
+
+fn gen_name() -> GLuint {
+    let mut mock = MOCK.lock().unwrap();
+    let name = mock.next_name;
+    mock.next_name += 1;
+    name
+}
+
+unsafe fn gen_names(count: GLsizei, names: *mut GLuint) {
+    for i in 0..count.max(0) as usize {
+        *names.add(i) = gen_name();
+    }
+}
+
+extern "system" fn viewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    record(
+        "glViewport",
+        &[x.into(), y.into(), width.into(), height.into()],
+    );
+    set(GL_VIEWPORT, [x, y, width, height]);
+}
+
+extern "system" fn scissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    record(
+        "glScissor",
+        &[x.into(), y.into(), width.into(), height.into()],
+    );
+    set(GL_SCISSOR_BOX, [x, y, width, height]);
+}
+
+extern "system" fn get_string(name: GLenum) -> *const GLubyte {
+    match name {
+        GL_VERSION => c"3.3.0 mock".as_ptr() as *const GLubyte,
+        _ => c"".as_ptr() as *const GLubyte,
+    }
+}
+
+extern "system" fn get_stringi(_name: GLenum, _index: GLuint) -> *const GLubyte {
+    std::ptr::null()
+}
+
+unsafe extern "system" fn get_integerv(name: GLenum, data: *mut GLint) {
+    let value = MOCK
+        .lock()
+        .unwrap()
+        .state
+        .integers
+        .get(&name)
+        .copied()
+        .unwrap_or_default();
+    let count = match name {
+        GL_VIEWPORT | GL_SCISSOR_BOX => 4,
+        _ => 1,
+    };
+
+    for (i, v) in value.iter().take(count).enumerate() {
+        *data.add(i) = *v;
+    }
+}
+
+unsafe extern "system" fn get_booleanv(name: GLenum, data: *mut GLboolean) {
+    let mut value = 0;
+    get_integerv(name, &mut value);
+    *data = (value != 0) as GLboolean;
+}
+
+extern "system" fn enable(cap: GLenum) {
+    record("glEnable", &[cap.into()]);
+    set_enabled(cap, true);
+}
+
+extern "system" fn disable(cap: GLenum) {
+    record("glDisable", &[cap.into()]);
+    set_enabled(cap, false);
+}
+
+extern "system" fn is_enabled(cap: GLenum) -> GLboolean {
+    MOCK.lock().unwrap().state.enabled.contains(&cap) as GLboolean
+}
+
+extern "system" fn clear(mask: GLbitfield) {
+    record("glClear", &[mask.into()]);
+}
+
+extern "system" fn depth_mask(flag: GLboolean) {
+    record("glDepthMask", &[flag.into()]);
+    set_one(GL_DEPTH_WRITEMASK, flag.into());
+}
+
+extern "system" fn push_debug_group(
+    source: GLenum,
+    id: GLuint,
+    _length: GLsizei,
+    _message: *const c_char,
+) {
+    record("glPushDebugGroup", &[source.into(), id.into()]);
+}
+
+extern "system" fn pop_debug_group() {
+    record("glPopDebugGroup", &[]);
+}
+
+extern "system" fn create_shader(kind: GLenum) -> GLuint {
+    record("glCreateShader", &[kind.into()]);
+    gen_name()
+}
+
+extern "system" fn shader_source(
+    shader: GLuint,
+    count: GLsizei,
+    _strings: *const *const c_char,
+    _lengths: *const GLint,
+) {
+    record("glShaderSource", &[shader.into(), count.into()]);
+}
+
+extern "system" fn compile_shader(shader: GLuint) {
+    record("glCompileShader", &[shader.into()]);
+}
+
+// Compiling and linking always succeed, with an empty info log.
+unsafe extern "system" fn get_object_iv(_object: GLuint, name: GLenum, value: *mut GLint) {
+    *value = match name {
+        GL_COMPILE_STATUS | GL_LINK_STATUS => GL_TRUE as GLint,
+        _ => 0,
+    };
+}
+
+unsafe extern "system" fn get_info_log(
+    _object: GLuint,
+    _size: GLsizei,
+    length: *mut GLsizei,
+    _log: *mut c_char,
+) {
+    if !length.is_null() {
+        *length = 0;
+    }
+}
+
+extern "system" fn delete_shader(shader: GLuint) {
+    record("glDeleteShader", &[shader.into()]);
+}
+
+extern "system" fn create_program() -> GLuint {
+    record("glCreateProgram", &[]);
+    gen_name()
+}
+
+extern "system" fn attach_shader(program: GLuint, shader: GLuint) {
+    record("glAttachShader", &[program.into(), shader.into()]);
+}
+
+extern "system" fn link_program(program: GLuint) {
+    record("glLinkProgram", &[program.into()]);
+}
+
+extern "system" fn delete_program(program: GLuint) {
+    record("glDeleteProgram", &[program.into()]);
+}
+
+extern "system" fn use_program(program: GLuint) {
+    record("glUseProgram", &[program.into()]);
+    set_one(GL_CURRENT_PROGRAM, program as GLint);
+}
+
+extern "system" fn get_location(_program: GLuint, _name: *const c_char) -> GLint {
+    0
+}
+
+extern "system" fn uniform_1i(location: GLint, value: GLint) {
+    record("glUniform1i", &[location.into(), value.into()]);
+}
+
+extern "system" fn uniform_matrix_4fv(
+    location: GLint,
+    count: GLsizei,
+    transpose: GLboolean,
+    _value: *const GLfloat,
+) {
+    record(
+        "glUniformMatrix4fv",
+        &[location.into(), count.into(), transpose.into()],
+    );
+}
+
+unsafe extern "system" fn gen_buffers(count: GLsizei, buffers: *mut GLuint) {
+    record("glGenBuffers", &[count.into()]);
+    gen_names(count, buffers);
+}
+
+extern "system" fn delete_buffers(count: GLsizei, _buffers: *const GLuint) {
+    record("glDeleteBuffers", &[count.into()]);
+}
+
+extern "system" fn bind_buffer(target: GLenum, buffer: GLuint) {
+    record("glBindBuffer", &[target.into(), buffer.into()]);
+    match target {
+        GL_ARRAY_BUFFER => set_one(GL_ARRAY_BUFFER_BINDING, buffer as GLint),
+        GL_ELEMENT_ARRAY_BUFFER => set_one(GL_ELEMENT_ARRAY_BUFFER_BINDING, buffer as GLint),
+        _ => {}
+    }
+}
+
+extern "system" fn bind_framebuffer(target: GLenum, framebuffer: GLuint) {
+    record("glBindFramebuffer", &[target.into(), framebuffer.into()]);
+    match target {
+        GL_DRAW_FRAMEBUFFER => set_one(GL_DRAW_FRAMEBUFFER_BINDING, framebuffer as GLint),
+        GL_READ_FRAMEBUFFER => set_one(GL_READ_FRAMEBUFFER_BINDING, framebuffer as GLint),
+        GL_FRAMEBUFFER => {
+            set_one(GL_DRAW_FRAMEBUFFER_BINDING, framebuffer as GLint);
+            set_one(GL_READ_FRAMEBUFFER_BINDING, framebuffer as GLint);
+        }
+        _ => {}
+    }
+}
+
+extern "system" fn buffer_data(
+    target: GLenum,
+    size: GLsizeiptr,
+    _data: *const c_void,
+    usage: GLenum,
+) {
+    record("glBufferData", &[target.into(), size as i64, usage.into()]);
+}
+
+unsafe extern "system" fn gen_vertex_arrays(count: GLsizei, arrays: *mut GLuint) {
+    record("glGenVertexArrays", &[count.into()]);
+    gen_names(count, arrays);
+}
+
+extern "system" fn delete_vertex_arrays(count: GLsizei, _arrays: *const GLuint) {
+    record("glDeleteVertexArrays", &[count.into()]);
+}
+
+extern "system" fn bind_vertex_array(array: GLuint) {
+    record("glBindVertexArray", &[array.into()]);
+    set_one(GL_VERTEX_ARRAY_BINDING, array as GLint);
+}
+
+extern "system" fn enable_vertex_attrib_array(index: GLuint) {
+    record("glEnableVertexAttribArray", &[index.into()]);
+}
+
+extern "system" fn vertex_attrib_pointer(
+    index: GLuint,
+    size: GLint,
+    kind: GLenum,
+    normalized: GLboolean,
+    stride: GLsizei,
+    _offset: *const c_void,
+) {
+    record(
+        "glVertexAttribPointer",
+        &[
+            index.into(),
+            size.into(),
+            kind.into(),
+            normalized.into(),
+            stride.into(),
+        ],
+    );
+}
+
+extern "system" fn active_texture(texture: GLenum) {
+    record("glActiveTexture", &[texture.into()]);
+    set_one(GL_ACTIVE_TEXTURE, texture as GLint);
+}
+
+extern "system" fn bind_texture(target: GLenum, texture: GLuint) {
+    record("glBindTexture", &[target.into(), texture.into()]);
+    if target == GL_TEXTURE_2D {
+        set_one(GL_TEXTURE_BINDING_2D, texture as GLint);
+    }
+}
+
+unsafe extern "system" fn gen_textures(count: GLsizei, textures: *mut GLuint) {
+    record("glGenTextures", &[count.into()]);
+    gen_names(count, textures);
+}
+
+extern "system" fn delete_textures(count: GLsizei, _textures: *const GLuint) {
+    record("glDeleteTextures", &[count.into()]);
+}
+
+extern "system" fn tex_parameteri(target: GLenum, name: GLenum, value: GLint) {
+    record(
+        "glTexParameteri",
+        &[target.into(), name.into(), value.into()],
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "system" fn tex_image_2d(
+    target: GLenum,
+    level: GLint,
+    internal_format: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    border: GLint,
+    format: GLenum,
+    kind: GLenum,
+    _pixels: *const c_void,
+) {
+    record(
+        "glTexImage2D",
+        &[
+            target.into(),
+            level.into(),
+            internal_format.into(),
+            width.into(),
+            height.into(),
+            border.into(),
+            format.into(),
+            kind.into(),
+        ],
+    );
+}
+
+/// Reads back transparent black, nothing is ever drawn.
+unsafe extern "system" fn read_pixels(
+    x: GLint,
+    y: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    format: GLenum,
+    ty: GLenum,
+    data: *mut c_void,
+) {
+    record(
+        "glReadPixels",
+        &[
+            x.into(),
+            y.into(),
+            width.into(),
+            height.into(),
+            format.into(),
+            ty.into(),
+        ],
+    );
+    // RGBA bytes, the only format the crate reads.
+    std::ptr::write_bytes(
+        data as *mut u8,
+        0,
+        width.max(0) as usize * height.max(0) as usize * 4,
+    );
+}
+
+extern "system" fn pixel_storei(name: GLenum, value: GLint) {
+    record("glPixelStorei", &[name.into(), value.into()]);
+    set_one(name, value);
+}
+
+extern "system" fn blend_equation(mode: GLenum) {
+    record("glBlendEquation", &[mode.into()]);
+    set_one(GL_BLEND_EQUATION_RGB, mode as GLint);
+    set_one(GL_BLEND_EQUATION_ALPHA, mode as GLint);
+}
+
+extern "system" fn blend_equation_separate(mode_rgb: GLenum, mode_alpha: GLenum) {
+    record(
+        "glBlendEquationSeparate",
+        &[mode_rgb.into(), mode_alpha.into()],
+    );
+    set_one(GL_BLEND_EQUATION_RGB, mode_rgb as GLint);
+    set_one(GL_BLEND_EQUATION_ALPHA, mode_alpha as GLint);
+}
+
+extern "system" fn blend_func(src: GLenum, dst: GLenum) {
+    record("glBlendFunc", &[src.into(), dst.into()]);
+    blend_func_separate(src, dst, src, dst);
+}
+
+extern "system" fn blend_func_separate(
+    src_rgb: GLenum,
+    dst_rgb: GLenum,
+    src_alpha: GLenum,
+    dst_alpha: GLenum,
+) {
+    record(
+        "glBlendFuncSeparate",
+        &[
+            src_rgb.into(),
+            dst_rgb.into(),
+            src_alpha.into(),
+            dst_alpha.into(),
+        ],
+    );
+    set_one(GL_BLEND_SRC_RGB, src_rgb as GLint);
+    set_one(GL_BLEND_DST_RGB, dst_rgb as GLint);
+    set_one(GL_BLEND_SRC_ALPHA, src_alpha as GLint);
+    set_one(GL_BLEND_DST_ALPHA, dst_alpha as GLint);
+}
+
+extern "system" fn draw_elements(
+    mode: GLenum,
+    count: GLsizei,
+    kind: GLenum,
+    offset: *const c_void,
+) {
+    record(
+        "glDrawElements",
+        &[mode.into(), count.into(), kind.into(), offset as i64],
+    );
+}
+
+extern "system" fn draw_elements_base_vertex(
+    mode: GLenum,
+    count: GLsizei,
+    kind: GLenum,
+    offset: *const c_void,
+    base_vertex: GLint,
+) {
+    record(
+        "glDrawElementsBaseVertex",
+        &[
+            mode.into(),
+            count.into(),
+            kind.into(),
+            offset as i64,
+            base_vertex.into(),
+        ],
+    );
+}
+
+/// The mock's implementation of `name`, null for functions it doesn't provide.
+pub(crate) fn proc_address(name: &str) -> *const c_void {
+    match name {
+        "glViewport" => viewport as *const c_void,
+        "glScissor" => scissor as *const c_void,
+        "glGetString" => get_string as *const c_void,
+        "glGetStringi" => get_stringi as *const c_void,
+        "glGetIntegerv" => get_integerv as *const c_void,
+        "glGetBooleanv" => get_booleanv as *const c_void,
+        "glEnable" => enable as *const c_void,
+        "glDisable" => disable as *const c_void,
+        "glIsEnabled" => is_enabled as *const c_void,
+        "glClear" => clear as *const c_void,
+        "glDepthMask" => depth_mask as *const c_void,
+        "glPushDebugGroup" => push_debug_group as *const c_void,
+        "glPopDebugGroup" => pop_debug_group as *const c_void,
+        "glCreateShader" => create_shader as *const c_void,
+        "glShaderSource" => shader_source as *const c_void,
+        "glCompileShader" => compile_shader as *const c_void,
+        "glGetShaderiv" | "glGetProgramiv" => get_object_iv as *const c_void,
+        "glGetShaderInfoLog" | "glGetProgramInfoLog" => get_info_log as *const c_void,
+        "glDeleteShader" => delete_shader as *const c_void,
+        "glCreateProgram" => create_program as *const c_void,
+        "glAttachShader" => attach_shader as *const c_void,
+        "glLinkProgram" => link_program as *const c_void,
+        "glDeleteProgram" => delete_program as *const c_void,
+        "glUseProgram" => use_program as *const c_void,
+        "glGetUniformLocation" | "glGetAttribLocation" => get_location as *const c_void,
+        "glUniform1i" => uniform_1i as *const c_void,
+        "glUniformMatrix4fv" => uniform_matrix_4fv as *const c_void,
+        "glGenBuffers" => gen_buffers as *const c_void,
+        "glDeleteBuffers" => delete_buffers as *const c_void,
+        "glBindBuffer" => bind_buffer as *const c_void,
+        "glBindFramebuffer" => bind_framebuffer as *const c_void,
+        "glBufferData" => buffer_data as *const c_void,
+        "glGenVertexArrays" => gen_vertex_arrays as *const c_void,
+        "glDeleteVertexArrays" => delete_vertex_arrays as *const c_void,
+        "glBindVertexArray" => bind_vertex_array as *const c_void,
+        "glEnableVertexAttribArray" => enable_vertex_attrib_array as *const c_void,
+        "glVertexAttribPointer" => vertex_attrib_pointer as *const c_void,
+        "glActiveTexture" => active_texture as *const c_void,
+        "glBindTexture" => bind_texture as *const c_void,
+        "glGenTextures" => gen_textures as *const c_void,
+        "glDeleteTextures" => delete_textures as *const c_void,
+        "glTexParameteri" => tex_parameteri as *const c_void,
+        "glTexImage2D" => tex_image_2d as *const c_void,
+        "glPixelStorei" => pixel_storei as *const c_void,
+        "glReadPixels" => read_pixels as *const c_void,
+        "glBlendEquation" => blend_equation as *const c_void,
+        "glBlendEquationSeparate" => blend_equation_separate as *const c_void,
+        "glBlendFunc" => blend_func as *const c_void,
+        "glBlendFuncSeparate" => blend_func_separate as *const c_void,
+        "glDrawElements" => draw_elements as *const c_void,
+        "glDrawElementsBaseVertex" => draw_elements_base_vertex as *const c_void,
+        _ => std::ptr::null(),
+    }
+}
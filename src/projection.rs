@@ -0,0 +1,27 @@
+//! Experimental: drawing the overlay with a caller-supplied projection, for UI placed in the 3D
+//! scene (billboards, in-world panels) instead of on top of the screen.
+//!
+//! While a projection is set the overlay is drawn by the crate's own GL 3.3 renderer instead of
+//! `imgui-opengl-renderer`. Things to keep in mind:
+//! - The matrix receives ImGui's coordinates as they are: pixels, x to the right, y downwards,
+//!   origin at the top left of the display, z = 0, w = 1. Fold any model-view transform (and a
+//!   y flip, if your world is y-up) into it. It is column-major and uploaded to GL unchanged.
+//! - ImGui's clip rects are screen-space rectangles, so scissoring is off. Content scrolled out
+//!   of a child window or cut by a window border is drawn anyway; keep in-world UIs simple.
+//! - Face culling is off, so panels stay visible from behind (mirrored).
+//! - Depth testing follows `HookConfig::disable_depth_test`, which is on by default and makes the
+//!   overlay draw over the scene. Turn it off to let scene geometry occlude the UI.
+//! - Mouse input still maps to screen space and won't line up with a transformed UI.
+
+use std::sync::Mutex;
+
+static PROJECTION: Mutex<Option<[[f32; 4]; 4]>> = Mutex::new(None);
+
+/// Replaces the overlay's orthographic projection from the next frame on, `None` goes back to it.
+pub fn set_projection(projection: Option<[[f32; 4]; 4]>) {
+    *PROJECTION.lock().unwrap() = projection;
+}
+
+pub(crate) fn projection() -> Option<[[f32; 4]; 4]> {
+    *PROJECTION.lock().unwrap()
+}
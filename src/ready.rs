@@ -0,0 +1,87 @@
+//! The one-shot "overlay is up" callback, see [`set_on_ready`], and [`wait_until_initialized`] /
+//! [`is_overlay_ready`].
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+type ReadyFn = Box<dyn FnOnce() + Send>;
+
+static READY: AtomicBool = AtomicBool::new(false);
+static ON_READY: Mutex<Option<ReadyFn>> = Mutex::new(None);
+
+static INITIALIZED: Mutex<bool> = Mutex::new(false);
+static INITIALIZED_CHANGED: Condvar = Condvar::new();
+
+/// Blocks until the overlay's ImGui context and renderer exist, for at most `timeout`. Returns
+/// whether they do.
+///
+/// They are created on the first frame the game presents after [`init_hook`](crate::init_hook).
+/// Fonts added with [`add_font_from_bytes`](crate::add_font_from_bytes) before that are baked
+/// then, later ones need [`reload_fonts`](crate::reload_fonts); the callbacks (render, ini,
+/// ready) can be set at any time.
+///
+/// Don't call it on the render thread, the frame it waits for never comes.
+pub fn wait_until_initialized(timeout: Duration) -> bool {
+    let initialized = INITIALIZED.lock().unwrap();
+    let (initialized, _) = INITIALIZED_CHANGED
+        .wait_timeout_while(initialized, timeout, |initialized| !*initialized)
+        .unwrap();
+    *initialized
+}
+
+/// Whether the overlay's ImGui context and renderer exist right now: false before the first
+/// frame and after [`shutdown_hook`](crate::shutdown_hook). Doesn't block.
+pub fn is_overlay_ready() -> bool {
+    *INITIALIZED.lock().unwrap()
+}
+
+/// Called once the overlay state has been created, wakes [`wait_until_initialized`].
+pub(crate) fn mark_initialized() {
+    *INITIALIZED.lock().unwrap() = true;
+    INITIALIZED_CHANGED.notify_all();
+}
+
+/// Called when the overlay state is dropped.
+pub(crate) fn mark_uninitialized() {
+    *INITIALIZED.lock().unwrap() = false;
+}
+
+/// Runs `f` once, on the render thread, right after the first overlay frame has been presented.
+///
+/// The game's GL context is current at that point and known to work with the overlay, which
+/// makes it the place to create GL resources. If that point has already passed, `f` runs
+/// immediately on the calling thread instead (without a GL context, unless the caller has one).
+/// Setting another callback before then replaces the previous one.
+pub fn set_on_ready(f: impl FnOnce() + Send + 'static) {
+    let mut on_ready = ON_READY.lock().unwrap();
+
+    if READY.load(Ordering::Acquire) {
+        drop(on_ready);
+        f();
+    } else {
+        *on_ready = Some(Box::new(f));
+    }
+}
+
+/// Called after every presented frame, fires the callback the first time.
+pub(crate) fn mark_ready() {
+    if READY.load(Ordering::Acquire) {
+        return;
+    }
+
+    let callback = {
+        let mut on_ready = ON_READY.lock().unwrap();
+        READY.store(true, Ordering::Release);
+        on_ready.take()
+    };
+
+    // Outside the lock, the callback may well call `set_on_ready` itself.
+    if let Some(callback) = callback {
+        callback();
+    }
+}
@@ -0,0 +1,273 @@
+//! The consumer's UI, see [`add_render_callback`] and [`set_render_callback`], and drawing
+//! outside of windows, see [`set_background_draw`].
+
+use imgui::{Condition, DrawListMut, Ui, Window};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Mutex,
+};
+
+use crate::{
+    config,
+    lifecycle::{self, HookEvent},
+    unwind,
+};
+
+pub(crate) type RenderFn = Box<dyn FnMut(&Ui) + Send>;
+type BackgroundFn = Box<dyn FnMut(&DrawListMut) + Send>;
+
+/// Identifies a callback added with [`add_render_callback`], for removing it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+struct Entry {
+    id: CallbackId,
+    priority: i32,
+    /// `None` while the callback runs.
+    callback: Option<RenderFn>,
+}
+
+// Not kept in the overlay state: callbacks are added before the overlay is up, and run with its
+// lock held while they may add and remove callbacks themselves.
+static CALLBACKS: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+// The callback installed by `set_render_callback`, which replaces itself.
+static SET_CALLBACK: Mutex<Option<CallbackId>> = Mutex::new(None);
+static BACKGROUND: Mutex<Option<BackgroundFn>> = Mutex::new(None);
+// Bumped whenever the background callback is set or cleared, see `draw_background`.
+static BACKGROUND_CHANGES: AtomicU64 = AtomicU64::new(0);
+// The ImGui demo's checkbox in the placeholder window.
+static SHOW_IMGUI_DEMO: AtomicBool = AtomicBool::new(true);
+
+/// The window shown while no render callback is set, see `HookConfig::demo_window`. Position and
+/// size only apply the first time it appears without a saved layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DemoWindow {
+    pub title: String,
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl Default for DemoWindow {
+    fn default() -> Self {
+        DemoWindow {
+            title: "opengl-imgui-hook".to_owned(),
+            position: [20.0, 20.0],
+            size: [300.0, 110.0],
+        }
+    }
+}
+
+/// Adds a callback building part of the overlay's UI, called on the render thread once per frame
+/// between starting and rendering it. Without any callback a demo is shown, see
+/// `HookConfig::demo_window`.
+///
+/// All callbacks submit to the same frame, lower `priority` first; callbacks of equal priority
+/// run in the order they were added. A callback that panics is removed, which is logged and
+/// reported as [`HookEvent::Error`].
+pub fn add_render_callback(priority: i32, f: impl FnMut(&Ui) + Send + 'static) -> CallbackId {
+    let id = CallbackId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+    CALLBACKS.lock().unwrap().push(Entry {
+        id,
+        priority,
+        callback: Some(Box::new(f)),
+    });
+
+    id
+}
+
+/// Removes a callback added with [`add_render_callback`]. Does nothing if it is gone already.
+pub fn remove_render_callback(id: CallbackId) {
+    CALLBACKS.lock().unwrap().retain(|entry| entry.id != id);
+}
+
+/// Sets the callback building the overlay's UI, at priority 0. Replaces the one set previously
+/// through this function, callbacks added with [`add_render_callback`] stay.
+pub fn set_render_callback(f: impl FnMut(&Ui) + Send + 'static) {
+    let mut set_callback = SET_CALLBACK.lock().unwrap();
+
+    if let Some(id) = set_callback.take() {
+        remove_render_callback(id);
+    }
+    *set_callback = Some(add_render_callback(0, f));
+}
+
+/// Sets the callback drawing straight onto the screen, for lines and boxes over the game (ESP
+/// style) without a window around them. Called every frame with ImGui's background draw list,
+/// after the render callbacks; what it draws is behind all ImGui windows. Replaces the previous
+/// one. Removed if it panics, like a render callback.
+pub fn set_background_draw(f: impl FnMut(&DrawListMut) + Send + 'static) {
+    let mut background = BACKGROUND.lock().unwrap();
+    *background = Some(Box::new(f));
+    BACKGROUND_CHANGES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Removes the callback set with [`set_background_draw`].
+pub fn clear_background_draw() {
+    let mut background = BACKGROUND.lock().unwrap();
+    *background = None;
+    BACKGROUND_CHANGES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Draws the background callback's shapes into the current frame.
+pub(crate) fn draw_background(ui: &Ui) {
+    // Outside the lock, the callback may well call `set_background_draw` itself.
+    let (callback, changes) = {
+        let mut background = BACKGROUND.lock().unwrap();
+        (background.take(), BACKGROUND_CHANGES.load(Ordering::Relaxed))
+    };
+
+    if let Some(mut callback) = callback {
+        let draw_list = ui.get_background_draw_list();
+        if unwind::catch_panic("running the background draw callback", || callback(&draw_list))
+            .is_none()
+        {
+            return report_removed("the background draw callback".to_owned());
+        }
+        // Unless it was replaced or cleared in the meantime.
+        let mut background = BACKGROUND.lock().unwrap();
+        if BACKGROUND_CHANGES.load(Ordering::Relaxed) == changes {
+            *background = Some(callback);
+        }
+    }
+}
+
+/// Tells about a callback removed for panicking, it would most likely panic again every frame.
+fn report_removed(what: String) {
+    log_error!("Removed {} after it panicked", what);
+    lifecycle::emit(HookEvent::Error(format!("{} panicked and was removed", what)));
+}
+
+/// The out-of-box UI: a window pointing at the render callbacks, and ImGui's demo.
+fn draw_demo(ui: &Ui) {
+    let Some(demo_window) = config::read(|config| config.demo_window.clone()) else {
+        return;
+    };
+
+    let mut show_imgui_demo = SHOW_IMGUI_DEMO.load(Ordering::Relaxed);
+    Window::new(&demo_window.title)
+        .position(demo_window.position, Condition::FirstUseEver)
+        .size(demo_window.size, Condition::FirstUseEver)
+        .build(ui, || {
+            ui.text("No render callback set, see set_render_callback.");
+            ui.checkbox("Show ImGui demo", &mut show_imgui_demo);
+        });
+    if show_imgui_demo {
+        ui.show_demo_window(&mut show_imgui_demo);
+    }
+    SHOW_IMGUI_DEMO.store(show_imgui_demo, Ordering::Relaxed);
+}
+
+/// Submits the frame's windows.
+pub(crate) fn draw(ui: &Ui) {
+    let mut order: Vec<_> = CALLBACKS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|entry| (entry.priority, entry.id.0))
+        .collect();
+    if order.is_empty() {
+        draw_demo(ui);
+        return;
+    }
+    order.sort_unstable();
+
+    for (_, id) in order {
+        // Outside the lock, callbacks may well add or remove callbacks themselves.
+        let callback = CALLBACKS
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|entry| entry.id.0 == id)
+            .and_then(|entry| entry.callback.take());
+
+        if let Some(mut callback) = callback {
+            if unwind::catch_panic("running a render callback", || callback(ui)).is_none() {
+                remove_render_callback(CallbackId(id));
+                report_removed(format!("render callback {}", id));
+                continue;
+            }
+            // Unless it was removed in the meantime.
+            if let Some(entry) = CALLBACKS
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .find(|entry| entry.id.0 == id)
+            {
+                entry.callback = Some(callback);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lifecycle::set_lifecycle_callback, test_util};
+    use std::sync::Arc;
+
+    #[test]
+    fn panicking_render_callback_is_removed_and_reported() {
+        let _lock = test_util::lock();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        set_lifecycle_callback(move |event| seen.lock().unwrap().push(event));
+        let mut imgui = imgui::Context::create();
+
+        let id = add_render_callback(0, |_| panic!("in the render callback"));
+        let ui = imgui.frame();
+        draw(&ui);
+        drop(ui);
+
+        assert!(CALLBACKS.lock().unwrap().iter().all(|entry| entry.id != id));
+        assert_eq!(
+            *events.lock().unwrap(),
+            [HookEvent::Error(format!(
+                "render callback {} panicked and was removed",
+                id.0
+            ))]
+        );
+        set_lifecycle_callback(|_| {});
+    }
+
+    #[test]
+    fn panicking_background_draw_is_removed_and_reported() {
+        let _lock = test_util::lock();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        set_lifecycle_callback(move |event| seen.lock().unwrap().push(event));
+        let mut imgui = imgui::Context::create();
+
+        set_background_draw(|_| panic!("in the background draw callback"));
+        let ui = imgui.frame();
+        draw_background(&ui);
+        drop(ui);
+
+        assert!(BACKGROUND.lock().unwrap().is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [HookEvent::Error(
+                "the background draw callback panicked and was removed".to_owned()
+            )]
+        );
+        set_lifecycle_callback(|_| {});
+    }
+
+    #[test]
+    fn background_draw_can_be_cleared() {
+        let _lock = test_util::lock();
+        let mut imgui = imgui::Context::create();
+
+        set_background_draw(|_| {});
+        clear_background_draw();
+        assert!(BACKGROUND.lock().unwrap().is_none());
+
+        // Also by the callback itself.
+        set_background_draw(|_| clear_background_draw());
+        let ui = imgui.frame();
+        draw_background(&ui);
+        drop(ui);
+        assert!(BACKGROUND.lock().unwrap().is_none());
+    }
+}
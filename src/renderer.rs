@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Result};
+use imgui::{Context, DrawCmd, DrawData, Ui};
+use imgui_opengl_renderer::Renderer;
+use std::sync::Mutex;
+
+use crate::{
+    fonts, gl,
+    gl_renderer::{FontTexture, GlRenderer},
+};
+
+/// Which backend draws the overlay, see `HookConfig::renderer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RendererKind {
+    /// `imgui-opengl-renderer` on the game's GL context.
+    #[default]
+    OpenGl,
+    /// The crate's own renderer on the game's GL context, needs GL 3.3.
+    ///
+    /// Draws the same as `OpenGl` but doesn't depend on `imgui-opengl-renderer` keeping up with
+    /// imgui-rs, restores all the GL state it touches and keeps `GL_FRAMEBUFFER_SRGB` from
+    /// brightening the overlay.
+    Gl3,
+    /// Builds every frame but never touches GL, only counting what would have been drawn.
+    ///
+    /// For testing UI logic headlessly (together with `feed_message` and `run_headless_frame`),
+    /// not for use inside a game.
+    Null,
+}
+
+/// What the null renderer saw in the last frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draw_lists: usize,
+    pub commands: usize,
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+static NULL_STATS: Mutex<Option<DrawStats>> = Mutex::new(None);
+
+/// Stats of the last frame rendered by [`RendererKind::Null`], `None` before the first one.
+pub fn null_renderer_stats() -> Option<DrawStats> {
+    *NULL_STATS.lock().unwrap()
+}
+
+pub(crate) struct NullRenderer;
+
+impl NullRenderer {
+    fn new(imgui: &mut Context) -> Self {
+        // No texture to upload it to, but ImGui refuses to start a frame with an unbuilt atlas.
+        imgui.fonts().build_rgba32_texture();
+
+        NullRenderer
+    }
+
+    fn render(&self, ui: Ui) {
+        let draw_data = ui.render();
+
+        let mut stats = DrawStats::default();
+        for draw_list in draw_data.draw_lists() {
+            stats.draw_lists += 1;
+            stats.vertices += draw_list.vtx_buffer().len();
+            stats.indices += draw_list.idx_buffer().len();
+            stats.commands += draw_list
+                .commands()
+                .filter(|cmd| matches!(cmd, DrawCmd::Elements { .. }))
+                .count();
+        }
+
+        *NULL_STATS.lock().unwrap() = Some(stats);
+    }
+}
+
+pub(crate) enum OverlayRenderer {
+    OpenGl {
+        renderer: Renderer,
+        // The crate's own renderer on the same context, for what `imgui_opengl_renderer` can't
+        // do: drawing the last frame again and custom projections. `None` below GL 3.3.
+        gl3: Option<Box<GlRenderer>>,
+    },
+    Gl3 {
+        renderer: Box<GlRenderer>,
+        // Owned for as long as ImGui's font atlas points at it.
+        font_texture: FontTexture,
+    },
+    Null(NullRenderer),
+}
+
+// `imgui-opengl-renderer`'s shaders are GLSL 1.40, the crate's own are 3.30.
+const OPENGL_MIN_VERSION: (u32, u32) = (3, 1);
+const GL3_MIN_VERSION: (u32, u32) = (3, 3);
+
+/// Resolves the GL functions of the current context.
+fn load_gl() -> Result<()> {
+    // Init the loader (grabbing the func required)
+    gl_loader::init_gl();
+    if !gl::load() {
+        return Err(anyhow!("Failed resolving GL functions"));
+    }
+    gl::query_info();
+    log_info!(
+        "Game's GL context: {}",
+        gl::gl_version().as_deref().unwrap_or("unknown version")
+    );
+
+    Ok(())
+}
+
+/// Whether the game's context is at least `min`. Contexts whose version can't be told are given
+/// the benefit of the doubt.
+fn supports(min: (u32, u32)) -> bool {
+    gl::version_number().is_none_or(|version| version >= min)
+}
+
+/// The renderer to use for `kind` on the game's context: `Gl3` falls back to `OpenGl` on contexts
+/// too old for it. Fails if neither works there, rather than initializing one that draws nothing.
+fn pick_for_context(kind: RendererKind) -> Result<RendererKind> {
+    if kind == RendererKind::Gl3 && !supports(GL3_MIN_VERSION) && supports(OPENGL_MIN_VERSION) {
+        log_info!("GL context too old for the Gl3 renderer, using the OpenGl one");
+        return Ok(RendererKind::OpenGl);
+    }
+
+    let min = match kind {
+        RendererKind::OpenGl => OPENGL_MIN_VERSION,
+        RendererKind::Gl3 => GL3_MIN_VERSION,
+        RendererKind::Null => return Ok(kind),
+    };
+    match gl::version_number() {
+        Some(version) if version < min => Err(anyhow!(
+            "The game's GL context is version {}.{}, the {:?} renderer needs {}.{}",
+            version.0,
+            version.1,
+            kind,
+            min.0,
+            min.1
+        )),
+        _ => Ok(kind),
+    }
+}
+
+/// The projection set through `set_projection`, if that is compiled in.
+fn projection() -> Option<[[f32; 4]; 4]> {
+    #[cfg(feature = "experimental-projection")]
+    return crate::projection::projection();
+    #[cfg(not(feature = "experimental-projection"))]
+    None
+}
+
+impl OverlayRenderer {
+    pub(crate) fn new(kind: RendererKind, imgui: &mut Context) -> Result<Self> {
+        if kind != RendererKind::Null {
+            load_gl()?;
+        }
+
+        match pick_for_context(kind)? {
+            RendererKind::OpenGl => {
+                let renderer = Renderer::new(imgui, |s| gl::renderer_proc_address(s) as _);
+                let gl3 = if supports(GL3_MIN_VERSION) {
+                    GlRenderer::new()
+                        .map(Box::new)
+                        .map_err(|e| log_error!("Failed creating GL 3.3 renderer: {}", e))
+                        .ok()
+                } else {
+                    None
+                };
+                Ok(OverlayRenderer::OpenGl { renderer, gl3 })
+            }
+            RendererKind::Gl3 => {
+                let renderer = Box::new(GlRenderer::new()?);
+                let font_texture = FontTexture::upload(imgui)?;
+                Ok(OverlayRenderer::Gl3 {
+                    renderer,
+                    font_texture,
+                })
+            }
+            RendererKind::Null => Ok(OverlayRenderer::Null(NullRenderer::new(imgui))),
+        }
+    }
+
+    /// Rasterizes the fonts again for a UI drawn `scale` times larger, so text stays sharp.
+    pub(crate) fn rebuild_fonts(&mut self, imgui: &mut Context, scale: f32) -> Result<()> {
+        fonts::add_fonts(&mut imgui.fonts(), scale);
+        // Back to the default size in points, now with `scale` pixels per point.
+        imgui.io_mut().font_global_scale = 1.0 / scale;
+
+        match self {
+            OverlayRenderer::OpenGl { renderer, .. } => {
+                // It only uploads the font atlas when created.
+                *renderer = Renderer::new(imgui, |s| gl::renderer_proc_address(s) as _);
+            }
+            OverlayRenderer::Gl3 { font_texture, .. } => {
+                *font_texture = FontTexture::upload(imgui)?;
+            }
+            OverlayRenderer::Null(_) => {
+                imgui.fonts().build_rgba32_texture();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn render(&self, ui: Ui) {
+        match self {
+            OverlayRenderer::OpenGl { renderer, gl3 } => match projection() {
+                // Only the crate's own renderer takes a projection, without it nothing is drawn.
+                Some(projection) => {
+                    if let Some(gl3) = gl3 {
+                        gl3.render_to_game(ui.render(), Some(&projection));
+                    }
+                }
+                None => renderer.render(ui),
+            },
+            OverlayRenderer::Gl3 { renderer, .. } => {
+                renderer.render_to_game(ui.render(), projection().as_ref())
+            }
+            OverlayRenderer::Null(renderer) => renderer.render(ui),
+        }
+    }
+
+    /// Draws the draw data of the last frame again, without building a new one. Returns false if
+    /// that isn't possible and a new frame has to be built.
+    pub(crate) fn render_last_frame(&self) -> bool {
+        // Stays valid until the next frame is started.
+        let Some(draw_data) =
+            (unsafe { (imgui::sys::igGetDrawData() as *const DrawData).as_ref() })
+        else {
+            return false;
+        };
+
+        match self {
+            // `imgui_opengl_renderer` only renders straight from a `Ui`.
+            OverlayRenderer::OpenGl { gl3, .. } => match gl3 {
+                Some(renderer) => {
+                    renderer.render_to_game(draw_data, projection().as_ref());
+                    true
+                }
+                None => false,
+            },
+            OverlayRenderer::Gl3 { renderer, .. } => {
+                renderer.render_to_game(draw_data, projection().as_ref());
+                true
+            }
+            // Nothing was drawn, so there is nothing to redraw.
+            OverlayRenderer::Null(_) => true,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Reading back the finished frame, game and overlay together, see [`request_screenshot`].
+
+use std::{
+    mem,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use crate::gl::{self, GLenum, GLint, GL_READ_FRAMEBUFFER, GL_READ_FRAMEBUFFER_BINDING};
+
+const GL_UNSIGNED_BYTE: GLenum = 0x1401;
+const GL_RGBA: GLenum = 0x1908;
+const GL_VIEWPORT: GLenum = 0x0BA2;
+const GL_PACK_ROW_LENGTH: GLenum = 0x0D02;
+const GL_PACK_SKIP_ROWS: GLenum = 0x0D03;
+const GL_PACK_SKIP_PIXELS: GLenum = 0x0D04;
+const GL_PACK_ALIGNMENT: GLenum = 0x0D05;
+const GL_PIXEL_PACK_BUFFER: GLenum = 0x88EB;
+const GL_PIXEL_PACK_BUFFER_BINDING: GLenum = 0x88ED;
+
+/// One frame as presented, see [`request_screenshot`].
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit RGBA, top row first.
+    pub rgba: Vec<u8>,
+}
+
+static PENDING: Mutex<Vec<Sender<Screenshot>>> = Mutex::new(Vec::new());
+
+/// Asks for the next frame the overlay is built for, with the overlay on it unless hidden, e.g.
+/// for bug reports. The screenshot arrives on the returned channel after the frame is drawn;
+/// the channel disconnects instead if none could be taken (no GL, e.g. with
+/// `RendererKind::Null`).
+///
+/// Covers the game's viewport at the time of the present, usually the whole window, read from
+/// the framebuffer the overlay is drawn into (see `set_target_framebuffer`). Frames are
+/// only read back when asked for, there is no cost otherwise.
+pub fn request_screenshot() -> Receiver<Screenshot> {
+    let (sender, receiver) = mpsc::channel();
+    PENDING.lock().unwrap().push(sender);
+    receiver
+}
+
+/// Hands the frame just drawn to everyone who asked for it. Call after the overlay is drawn and
+/// the GL state restored, before the swap.
+pub(crate) fn capture_if_requested() {
+    let pending = mem::take(&mut *PENDING.lock().unwrap());
+    if pending.is_empty() {
+        return;
+    }
+
+    // Dropping the senders without a screenshot disconnects the receivers.
+    let Some(screenshot) = read_frame() else {
+        log_error!("Failed taking screenshot, no GL");
+        return;
+    };
+    for sender in pending {
+        // The receiver may be gone already, nobody to tell.
+        let _ = sender.send(screenshot.clone());
+    }
+}
+
+fn read_frame() -> Option<Screenshot> {
+    let gl = gl::get()?;
+
+    let mut viewport = [0; 4];
+    unsafe { (gl.get_integerv)(GL_VIEWPORT, viewport.as_mut_ptr()) };
+    let [x, y, width, height] = viewport;
+    let (width, height) = (width.max(0) as usize, height.max(0) as usize);
+    let mut rgba = vec![0u8; width * height * 4];
+
+    // The game's pack state would pad or offset the rows, a bound pack buffer would take the
+    // pixels instead of our memory, and the game may read from its own framebuffer object.
+    let pack_state = [
+        (GL_PACK_ALIGNMENT, 1),
+        (GL_PACK_ROW_LENGTH, 0),
+        (GL_PACK_SKIP_ROWS, 0),
+        (GL_PACK_SKIP_PIXELS, 0),
+    ];
+    let mut saved: [GLint; 4] = [0; 4];
+    let mut pack_buffer = 0;
+    let mut read_framebuffer = 0;
+    unsafe {
+        for ((name, value), saved) in pack_state.iter().zip(&mut saved) {
+            (gl.get_integerv)(*name, saved);
+            (gl.pixel_storei)(*name, *value);
+        }
+        if let Some(bind_buffer) = gl.bind_buffer {
+            (gl.get_integerv)(GL_PIXEL_PACK_BUFFER_BINDING, &mut pack_buffer);
+            bind_buffer(GL_PIXEL_PACK_BUFFER, 0);
+        }
+        if let Some(bind_framebuffer) = gl.bind_framebuffer {
+            (gl.get_integerv)(GL_READ_FRAMEBUFFER_BINDING, &mut read_framebuffer);
+            bind_framebuffer(GL_READ_FRAMEBUFFER, gl::target_framebuffer());
+        }
+
+        (gl.read_pixels)(
+            x,
+            y,
+            width as i32,
+            height as i32,
+            GL_RGBA,
+            GL_UNSIGNED_BYTE,
+            rgba.as_mut_ptr().cast(),
+        );
+
+        if let Some(bind_framebuffer) = gl.bind_framebuffer {
+            bind_framebuffer(GL_READ_FRAMEBUFFER, read_framebuffer as u32);
+        }
+        if let Some(bind_buffer) = gl.bind_buffer {
+            bind_buffer(GL_PIXEL_PACK_BUFFER, pack_buffer as u32);
+        }
+        for ((name, _), saved) in pack_state.iter().zip(saved) {
+            (gl.pixel_storei)(*name, saved);
+        }
+    }
+
+    // GL's rows start at the bottom.
+    let row_len = width * 4;
+    for row in 0..height / 2 {
+        let (top, bottom) = rgba.split_at_mut((height - 1 - row) * row_len);
+        top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+
+    Some(Screenshot {
+        width: width as u32,
+        height: height as u32,
+        rgba,
+    })
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod tests {
+    use super::*;
+    use crate::{mock_gl, test_util};
+
+    #[test]
+    fn reads_from_target_framebuffer() {
+        let _lock = test_util::lock();
+        test_util::set_up_game_gl_state();
+        mock_gl::set_integer(GL_READ_FRAMEBUFFER_BINDING, [7, 0, 0, 0]);
+        gl::set_target_framebuffer(3);
+        let before = mock_gl::snapshot();
+
+        let screenshot = read_frame();
+        gl::set_target_framebuffer(0);
+
+        let screenshot = screenshot.unwrap();
+        assert_eq!((screenshot.width, screenshot.height), (300, 200));
+        let calls = mock_gl::calls();
+        let position = |name, args: &[i64]| {
+            calls
+                .iter()
+                .position(|call| call.name == name && call.args.starts_with(args))
+                .unwrap()
+        };
+        let read = position("glReadPixels", &[]);
+        assert!(position("glBindFramebuffer", &[GL_READ_FRAMEBUFFER.into(), 3]) < read);
+        assert!(position("glBindFramebuffer", &[GL_READ_FRAMEBUFFER.into(), 7]) > read);
+        assert_eq!(mock_gl::snapshot(), before);
+    }
+}
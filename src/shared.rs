@@ -0,0 +1,26 @@
+//! A slot for handing tool state from other threads to the code building the overlay UI.
+
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+};
+
+static SHARED_STATE: Mutex<Option<Arc<dyn Any + Send + Sync>>> = Mutex::new(None);
+
+/// Publishes `state` to the render thread, replacing whatever was stored before.
+///
+/// The value is shared, not copied: keep a clone of the `Arc` and use interior mutability
+/// (`Mutex`, atomics) inside `T` to update it while the overlay reads it.
+pub fn set_shared_state<T: Send + Sync + 'static>(state: Arc<T>) {
+    *SHARED_STATE.lock().unwrap() = Some(state);
+}
+
+/// The stored state, `None` if nothing was set or it is not a `T`.
+pub fn get_shared_state<T: Send + Sync + 'static>() -> Option<Arc<T>> {
+    let state = SHARED_STATE.lock().unwrap().clone()?;
+    state.downcast::<T>().ok()
+}
+
+pub fn clear_shared_state() {
+    *SHARED_STATE.lock().unwrap() = None;
+}
@@ -0,0 +1,58 @@
+//! Setting the overlay's ImGui style up, see [`set_style_callback`], and changing it while it runs,
+//! see [`with_style_mut`].
+
+use imgui::Style;
+use std::{mem, sync::Mutex};
+
+type StyleFn = Box<dyn FnOnce(&mut Style) + Send>;
+
+static PENDING: Mutex<Vec<StyleFn>> = Mutex::new(Vec::new());
+static ON_INIT: Mutex<Option<StyleFn>> = Mutex::new(None);
+
+/// Sets up the style (a theme) with `f` once, right after the overlay's ImGui context is created
+/// and before anything is drawn with it. Replaces a callback set before that.
+///
+/// Once the overlay is up `f` runs right away instead, on the calling thread; from inside the
+/// render callback it is queued like [`with_style_mut`]. A context created again after
+/// [`shutdown_hook`](crate::shutdown_hook) starts out with the default style.
+pub fn set_style_callback(f: impl FnOnce(&mut Style) + Send + 'static) {
+    // The frame being built holds the state, it can only be changed before the next one.
+    if crate::IN_DETOUR.get() {
+        return with_style_mut(f);
+    }
+
+    // Held while deciding, so the context can't come up in between and miss `f`.
+    let mut state = crate::STATE.lock().unwrap();
+    match state.as_mut() {
+        Some(state) => f(state.imgui.style_mut()),
+        None => *ON_INIT.lock().unwrap() = Some(Box::new(f)),
+    }
+}
+
+/// Queues `f` to modify the live style (colors, rounding, spacing, ...).
+///
+/// Can be called from any thread. `f` itself runs on the render thread, between two frames, right
+/// before the next one is started, so it never sees a half-drawn frame. Changes queued before the
+/// overlay is up are applied ahead of its first frame, in the order they were queued.
+pub fn with_style_mut(f: impl FnOnce(&mut Style) + Send + 'static) {
+    PENDING.lock().unwrap().push(Box::new(f));
+}
+
+/// Runs the callback set with [`set_style_callback`], call on a newly created context.
+pub(crate) fn init(style: &mut Style) {
+    let on_init = ON_INIT.lock().unwrap().take();
+
+    if let Some(f) = on_init {
+        f(style);
+    }
+}
+
+/// Runs the queued changes, call before starting a frame.
+pub(crate) fn apply(style: &mut Style) {
+    // Outside the lock, a change may queue another one (which then waits for the next frame).
+    let pending = mem::take(&mut *PENDING.lock().unwrap());
+
+    for f in pending {
+        f(style);
+    }
+}
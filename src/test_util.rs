@@ -0,0 +1,36 @@
+//! Helpers shared by the unit tests.
+
+use std::sync::{Mutex, MutexGuard};
+
+use crate::config::{set_config, HookConfig};
+#[cfg(feature = "mock-gl")]
+use crate::{
+    gl::{
+        GL_DEPTH_TEST, GL_DEPTH_WRITEMASK, GL_DRAW_FRAMEBUFFER_BINDING, GL_SCISSOR_BOX,
+        GL_SCISSOR_TEST, GL_VIEWPORT,
+    },
+    mock_gl,
+};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Serializes the tests touching the crate's statics (config, input state, the ImGui context, of
+/// which only one may exist). Starts them from the default config.
+pub(crate) fn lock() -> MutexGuard<'static, ()> {
+    // A failed test poisons it, the others still run.
+    let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_config(HookConfig::default());
+    guard
+}
+
+/// Installs the mock GL with game state that differs from everything the overlay sets.
+#[cfg(feature = "mock-gl")]
+pub(crate) fn set_up_game_gl_state() {
+    mock_gl::install();
+    mock_gl::set_enabled(GL_DEPTH_TEST, true);
+    mock_gl::set_enabled(GL_SCISSOR_TEST, true);
+    mock_gl::set_integer(GL_VIEWPORT, [10, 20, 300, 200]);
+    mock_gl::set_integer(GL_SCISSOR_BOX, [1, 2, 3, 4]);
+    mock_gl::set_integer(GL_DEPTH_WRITEMASK, [0; 4]);
+    mock_gl::set_integer(GL_DRAW_FRAMEBUFFER_BINDING, [7, 0, 0, 0]);
+}
@@ -0,0 +1,33 @@
+//! Keeping panics on our side of the hooked functions. Unwinding out of a detour or a window
+//! procedure into the game's code is undefined behavior and usually takes the game down.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Has panic messages printed to the hook's output, which the default hook (writing to stderr)
+/// misses. The previous hook still runs afterwards.
+pub(crate) fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            log_error!("Panic: {}", info);
+            previous(info);
+        }));
+    });
+}
+
+/// Runs `f`, returning `None` instead of unwinding if it panics.
+pub(crate) fn catch_panic<R>(what: &str, f: impl FnOnce() -> R) -> Option<R> {
+    // The caller is left with whatever `f` was in the middle of, it knows how to recover.
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => Some(result),
+        Err(_) => {
+            log_error!("Recovered from a panic while {}", what);
+            None
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Mapping the whole ImGui coordinate space onto a sub-rectangle of the game window.
+//!
+//! This is for games that render several views (split-screen, picture-in-picture cameras) into
+//! one window: with a target viewport set, ImGui's `(0, 0)..display_size` covers only that
+//! rectangle, both for drawing and for mouse input. It does not move individual ImGui windows
+//! around, use `Window::position` for that.
+
+use imgui::Io;
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, OnceLock,
+    },
+};
+use windows::Win32::{
+    Foundation::{HWND, RECT},
+    UI::WindowsAndMessaging::GetClientRect,
+};
+
+use crate::gl;
+
+/// A rectangle in client coordinates of the game window (origin top-left, in pixels).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    /// Intersects the rect with a `width` x `height` client area, `None` if nothing is left.
+    fn clamp_to(self, width: i32, height: i32) -> Option<Rect> {
+        let left = self.x.clamp(0, width);
+        let top = self.y.clamp(0, height);
+        let right = (self.x + self.width).clamp(0, width);
+        let bottom = (self.y + self.height).clamp(0, height);
+
+        if right > left && bottom > top {
+            Some(Rect {
+                x: left,
+                y: top,
+                width: right - left,
+                height: bottom - top,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Used when there is no game window to measure (headless frames).
+pub(crate) const DEFAULT_DISPLAY_SIZE: [f32; 2] = [1024.0, 1024.0];
+
+static TARGET: Mutex<Option<Rect>> = Mutex::new(None);
+// The target clamped to the window as of the last frame, used to translate mouse input.
+static ACTIVE: Mutex<Option<Rect>> = Mutex::new(None);
+
+// `f32` bits of the scale set through `set_ui_scale`, 1.0 to start with.
+static UI_SCALE: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// Lowest and highest accepted `set_ui_scale` values.
+const UI_SCALE_RANGE: (f32, f32) = (0.25, 8.0);
+
+// `f32` bits of the game window's DPI scale (100% = 1.0), see `update_dpi`.
+static DPI_SCALE: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// The DPI Windows considers 100%.
+const DEFAULT_DPI: u32 = 96;
+
+type GetDpiForWindowFn = unsafe extern "system" fn(HWND) -> u32;
+
+// Resolved on first use, importing it would keep the DLL from loading on older Windows.
+static GET_DPI_FOR_WINDOW: OnceLock<Option<GetDpiForWindowFn>> = OnceLock::new();
+
+/// DPI of the monitor `hwnd` is on, `None` for windows that aren't DPI aware (Windows scales
+/// those as a whole) and before Windows 10 1607, which lacks `GetDpiForWindow`.
+fn window_dpi(hwnd: HWND) -> Option<u32> {
+    // Polling mode asks every frame, user32.dll stays loaded for the life of the process.
+    let get_dpi_for_window = (*GET_DPI_FOR_WINDOW.get_or_init(|| {
+        crate::get_module_library("user32.dll", "GetDpiForWindow")
+            .ok()
+            .map(|function| unsafe { mem::transmute::<_, GetDpiForWindowFn>(function) })
+    }))?;
+
+    match unsafe { get_dpi_for_window(hwnd) } {
+        0 => None,
+        dpi => Some(dpi),
+    }
+}
+
+/// Picks up the DPI of `hwnd`, for when the overlay comes up on or moves to a window.
+pub(crate) fn update_dpi(hwnd: HWND) {
+    set_dpi(window_dpi(hwnd).unwrap_or(DEFAULT_DPI));
+}
+
+/// Sets the DPI the game window is shown at, e.g. from `WM_DPICHANGED`.
+pub(crate) fn set_dpi(dpi: u32) {
+    let scale = match dpi {
+        0 => 1.0,
+        dpi => dpi as f32 / DEFAULT_DPI as f32,
+    };
+
+    DPI_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+}
+
+/// Pixels per ImGui point: the UI scale on top of the monitor's DPI scaling.
+pub(crate) fn pixel_scale() -> f32 {
+    ui_scale() * f32::from_bits(DPI_SCALE.load(Ordering::Relaxed))
+}
+
+/// Scales the whole overlay (text, widgets, spacing) by `scale` from the next frame on.
+///
+/// Applies on top of the monitor's DPI scaling (150% on a 4K laptop panel, say), which the
+/// overlay follows by itself.
+/// Clamped to 0.25..=8, non-finite values reset it to 1. Fonts are rasterized again when the
+/// scale moves far enough from the one they were built for to look blurry.
+pub fn set_ui_scale(scale: f32) {
+    let scale = if scale.is_finite() {
+        scale.clamp(UI_SCALE_RANGE.0, UI_SCALE_RANGE.1)
+    } else {
+        1.0
+    };
+
+    UI_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+}
+
+pub fn ui_scale() -> f32 {
+    f32::from_bits(UI_SCALE.load(Ordering::Relaxed))
+}
+
+/// Aligns the overlay with `rect` of the game window from the next frame on.
+pub fn set_target_viewport(rect: Rect) {
+    *TARGET.lock().unwrap() = Some(rect);
+}
+
+/// Goes back to covering the whole game window.
+pub fn clear_target_viewport() {
+    *TARGET.lock().unwrap() = None;
+}
+
+pub fn target_viewport() -> Option<Rect> {
+    *TARGET.lock().unwrap()
+}
+
+/// Sets up `io.display_size`, the UI scale and the renderer's draw origin for the coming frame,
+/// from the current client area of `hwnd`.
+///
+/// Returns false if the client area is empty (a hidden window, or one not laid out yet), there is
+/// nothing to draw into then.
+pub(crate) fn apply(hwnd: HWND, io: &mut Io) -> bool {
+    let scale = pixel_scale();
+    // ImGui lays out in points, `display_framebuffer_scale` pixels each.
+    io.display_framebuffer_scale = [scale, scale];
+
+    let mut client = RECT::default();
+    let full_size = if unsafe { GetClientRect(hwnd, &mut client) }.as_bool() {
+        if client.right <= 0 || client.bottom <= 0 {
+            return false;
+        }
+        [client.right as f32, client.bottom as f32]
+    } else {
+        DEFAULT_DISPLAY_SIZE
+    };
+
+    let active = target_viewport().and_then(|rect| {
+        rect.clamp_to(full_size[0] as i32, full_size[1] as i32)
+            .map(|rect| (rect, full_size[1] as i32))
+    });
+
+    match active {
+        Some((rect, client_height)) => {
+            io.display_size = [rect.width as f32 / scale, rect.height as f32 / scale];
+            // GL counts from the bottom of the window.
+            gl::set_draw_origin(rect.x, client_height - (rect.y + rect.height));
+            *ACTIVE.lock().unwrap() = Some(rect);
+        }
+        None => {
+            io.display_size = [full_size[0] / scale, full_size[1] / scale];
+            gl::set_draw_origin(0, 0);
+            *ACTIVE.lock().unwrap() = None;
+        }
+    }
+
+    true
+}
+
+/// Translates a client-space mouse position into ImGui space, clamped to the target viewport.
+pub(crate) fn map_mouse_pos(pos: [f32; 2]) -> [f32; 2] {
+    let pos = match *ACTIVE.lock().unwrap() {
+        Some(rect) => [
+            (pos[0] - rect.x as f32).clamp(0.0, rect.width as f32),
+            (pos[1] - rect.y as f32).clamp(0.0, rect.height as f32),
+        ],
+        None => pos,
+    };
+
+    let scale = pixel_scale();
+    [pos[0] / scale, pos[1] / scale]
+}
@@ -0,0 +1,55 @@
+//! How named ImGui windows start out, see [`set_initial_window_states`].
+
+use imgui::{sys, Condition};
+use std::{
+    ffi::CString,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowState {
+    pub collapsed: bool,
+    /// Give the window keyboard focus. If several windows ask for it, the last one wins.
+    pub focused: bool,
+}
+
+static STATES: Mutex<Vec<(CString, WindowState)>> = Mutex::new(Vec::new());
+static FOCUS_APPLIED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the state windows start in, by window title (including any `##id` suffix).
+///
+/// This is advisory, with `Condition::FirstUseEver` semantics: the collapse state is applied
+/// the first time a window appears and only if the layout has nothing saved for it; focus is
+/// applied once, after the first frame, and only to a window submitted in that frame. The user
+/// can rearrange everything afterwards. Names containing nul bytes are ignored.
+pub fn set_initial_window_states(states: Vec<(String, WindowState)>) {
+    *STATES.lock().unwrap() = states
+        .into_iter()
+        .filter_map(|(name, state)| Some((CString::new(name).ok()?, state)))
+        .collect();
+}
+
+/// Applies the states, call after the frame's windows have been submitted.
+pub(crate) fn apply() {
+    let states = STATES.lock().unwrap();
+
+    for (name, state) in states.iter() {
+        // ImGui itself makes sure this only takes effect on first use.
+        unsafe {
+            sys::igSetWindowCollapsed_Str(
+                name.as_ptr(),
+                state.collapsed,
+                Condition::FirstUseEver as sys::ImGuiCond,
+            )
+        };
+    }
+
+    if !FOCUS_APPLIED.swap(true, Ordering::Relaxed) {
+        if let Some((name, _)) = states.iter().rev().find(|(_, state)| state.focused) {
+            unsafe { sys::igSetWindowFocus_Str(name.as_ptr()) };
+        }
+    }
+}